@@ -0,0 +1,365 @@
+//! Review changed snapshots and approve the ones that should become goldens
+//!
+//! Usage:
+//!     cargo run -p review -- GOLDENS [ACTUALS]
+//!
+//! Without an `ACTUALS` directory, the `.actual.png` files left next to the
+//! goldens by failed snapshot comparisons are reviewed instead.
+use iced::Alignment::Center;
+use iced::widget::{button, center, column, container, image, row, scrollable, slider, text};
+use iced::{ContentFit, Element, Fill, Size};
+
+use sleepy_hollow::export;
+use sleepy_hollow::regression::{self, Case, Status};
+use sleepy_hollow::snapshot::Tolerance;
+use std::env;
+use std::path::PathBuf;
+
+pub fn main() -> iced::Result {
+    iced::application(App::new, App::update, App::view)
+        .title("iced • snapshot review")
+        .run()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Decision {
+    Accept,
+    Reject,
+}
+
+struct Image {
+    handle: image::Handle,
+    size: Size<u32>,
+}
+
+#[derive(Default)]
+struct Images {
+    golden: Option<Image>,
+    actual: Option<Image>,
+    diff: Option<Image>,
+}
+
+#[derive(Default)]
+struct App {
+    goldens: PathBuf,
+    cases: Vec<Case>,
+    decisions: Vec<Option<Decision>>,
+    current: usize,
+    images: Images,
+    zoom: f32,
+    status: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Previous,
+    Next,
+    Decide(Decision),
+    Zoom(f32),
+    Apply,
+}
+
+impl App {
+    fn new() -> Self {
+        let mut arguments = env::args().skip(1);
+
+        let Some(goldens) = arguments.next().map(PathBuf::from) else {
+            return Self::failed("Usage: review GOLDENS [ACTUALS]".to_owned());
+        };
+
+        let cases = match arguments.next() {
+            Some(actuals) => {
+                regression::compare_directories(&goldens, actuals, &Tolerance::default())
+            }
+            None => regression::pending(&goldens, &Tolerance::default()),
+        };
+
+        match cases {
+            Ok(cases) => {
+                // Only changed snapshots need a review
+                let cases: Vec<Case> = cases
+                    .into_iter()
+                    .filter(|case| case.status != Status::Passed && case.actual.is_some())
+                    .collect();
+
+                let mut app = App {
+                    goldens,
+                    decisions: vec![None; cases.len()],
+                    cases,
+                    zoom: 1.0,
+                    ..App::default()
+                };
+
+                app.load_images();
+                app
+            }
+            Err(error) => Self::failed(error.to_string()),
+        }
+    }
+
+    fn failed(status: String) -> Self {
+        App {
+            zoom: 1.0,
+            status: Some(status),
+            ..App::default()
+        }
+    }
+
+    fn update(&mut self, message: Message) {
+        match message {
+            Message::Previous => {
+                self.current = self.current.saturating_sub(1);
+                self.load_images();
+            }
+            Message::Next => {
+                self.current = (self.current + 1).min(self.cases.len().saturating_sub(1));
+                self.load_images();
+            }
+            Message::Decide(decision) => {
+                if let Some(slot) = self.decisions.get_mut(self.current) {
+                    *slot = Some(decision);
+                }
+
+                // Move on to the next undecided snapshot, if any
+                if let Some(next) = self.decisions.iter().position(Option::is_none) {
+                    self.current = next;
+                    self.load_images();
+                }
+            }
+            Message::Zoom(zoom) => {
+                self.zoom = zoom;
+            }
+            Message::Apply => {
+                let mut written = 0;
+                let mut failure = None;
+                let mut index = 0;
+
+                // Applied cases are removed, so applying again does not approve them twice
+                while index < self.cases.len() {
+                    if self.decisions[index] != Some(Decision::Accept) {
+                        index += 1;
+                        continue;
+                    }
+
+                    let case = &self.cases[index];
+
+                    let golden = case
+                        .golden
+                        .clone()
+                        .unwrap_or_else(|| self.goldens.join(&case.name));
+
+                    if let Err(error) = regression::approve(case, &golden) {
+                        failure = Some(format!("Failed to approve {}: {error}", case.name));
+                        break;
+                    }
+
+                    let _ = self.cases.remove(index);
+                    let _ = self.decisions.remove(index);
+                    written += 1;
+                }
+
+                self.current = self.current.min(self.cases.len().saturating_sub(1));
+                self.load_images();
+
+                self.status = Some(failure.unwrap_or_else(|| format!("Wrote {written} golden(s)")));
+            }
+        }
+    }
+
+    fn load_images(&mut self) {
+        let Some(case) = self.cases.get(self.current) else {
+            self.images = Images::default();
+            return;
+        };
+
+        let load = |path: &Option<PathBuf>| {
+            let (screenshot, _) = export::load(path.as_ref()?).ok()?;
+
+            Some(Image {
+                handle: image::Handle::from_rgba(
+                    screenshot.size.width,
+                    screenshot.size.height,
+                    screenshot.bytes,
+                ),
+                size: screenshot.size,
+            })
+        };
+
+        self.images = Images {
+            golden: load(&case.golden),
+            actual: load(&case.actual),
+            diff: case.diff.as_ref().map(|diff| Image {
+                handle: image::Handle::from_rgba(
+                    diff.heatmap.size.width,
+                    diff.heatmap.size.height,
+                    diff.heatmap.bytes.clone(),
+                ),
+                size: diff.heatmap.size,
+            }),
+        };
+    }
+
+    fn image_element<'a>(
+        &'a self,
+        caption: &'a str,
+        image: &'a Option<Image>,
+    ) -> Element<'a, Message> {
+        let content: Element<'a, Message> = match image {
+            Some(image) => scrollable(
+                iced::widget::image(image.handle.clone())
+                    .width(image.size.width as f32 * self.zoom)
+                    .height(image.size.height as f32 * self.zoom)
+                    .content_fit(ContentFit::Fill)
+                    .filter_method(image::FilterMethod::Nearest),
+            )
+            .direction(scrollable::Direction::Both {
+                vertical: scrollable::Scrollbar::default(),
+                horizontal: scrollable::Scrollbar::default(),
+            })
+            .width(Fill)
+            .height(Fill)
+            .into(),
+            None => center(text("None")).into(),
+        };
+
+        column![
+            text(caption).size(14),
+            container(content)
+                .width(Fill)
+                .height(Fill)
+                .padding(10)
+                .style(container::rounded_box)
+        ]
+        .spacing(5)
+        .width(Fill)
+        .into()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let decided = self.decisions.iter().filter(|d| d.is_some()).count();
+        let accepted = self
+            .decisions
+            .iter()
+            .filter(|d| **d == Some(Decision::Accept))
+            .count();
+
+        let header = row![
+            container(text("𝓢𝓵𝓮𝓮𝓹𝔂 𝓗𝓸𝓵𝓵𝓸𝔀").shaping(text::Shaping::Advanced))
+                .width(Fill)
+                .padding(5),
+            container(
+                row![
+                    text(format!("Zoom {:.0}%", self.zoom * 100.0)).size(14),
+                    slider(0.25..=8.0, self.zoom, Message::Zoom)
+                        .step(0.25)
+                        .width(150),
+                    button("Previous").on_press(Message::Previous),
+                    button("Next").on_press(Message::Next),
+                ]
+                .spacing(15)
+                .align_y(Center)
+            )
+            .padding([5, 10])
+            .style(|theme: &iced::Theme| {
+                container::Style {
+                    background: Some(
+                        theme
+                            .extended_palette()
+                            .primary
+                            .strong
+                            .color
+                            .scale_alpha(0.25)
+                            .into(),
+                    ),
+                    text_color: Some(theme.extended_palette().primary.base.color),
+                    border: iced::Border {
+                        radius: 5.0.into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            }),
+            container(
+                button(text(format!("Write {accepted} approved")))
+                    .on_press_maybe((accepted > 0).then_some(Message::Apply))
+            )
+            .padding(5)
+        ]
+        .padding([0, 20])
+        .spacing(10)
+        .align_y(Center);
+
+        let display_content: Element<'_, Message> = match self.cases.get(self.current) {
+            None => center(text("No changed snapshots to review.").size(18)).into(),
+            Some(case) => {
+                let status = match &case.status {
+                    Status::Failed => "changed".to_owned(),
+                    Status::SizeMismatch { expected, found } => format!(
+                        "size changed from {}x{} to {}x{}",
+                        expected.width, expected.height, found.width, found.height
+                    ),
+                    Status::New => "new".to_owned(),
+                    Status::Passed | Status::Missing => "unchanged".to_owned(),
+                };
+
+                let metrics = match (&case.diff, &case.metrics) {
+                    (Some(diff), Some(metrics)) => format!(
+                        " - {} pixels differ, PSNR {:.2} dB, SSIM {:.4}",
+                        diff.over_threshold, metrics.psnr, metrics.ssim
+                    ),
+                    _ => String::new(),
+                };
+
+                let decision = match self.decisions[self.current] {
+                    Some(Decision::Accept) => "accepted",
+                    Some(Decision::Reject) => "rejected",
+                    None => "undecided",
+                };
+
+                let info = container(
+                    text(format!(
+                        "{}/{} ({decided} reviewed) - {} - {status}{metrics} - {decision}",
+                        self.current + 1,
+                        self.cases.len(),
+                        case.name
+                    ))
+                    .size(12),
+                )
+                .align_right(Fill);
+
+                column![
+                    row![
+                        self.image_element("Golden", &self.images.golden),
+                        self.image_element("Actual", &self.images.actual),
+                        self.image_element("Diff", &self.images.diff),
+                    ]
+                    .spacing(10)
+                    .height(600),
+                    row![
+                        button("Accept").on_press(Message::Decide(Decision::Accept)),
+                        button("Reject")
+                            .style(button::secondary)
+                            .on_press(Message::Decide(Decision::Reject)),
+                        info,
+                    ]
+                    .spacing(10)
+                    .align_y(Center),
+                ]
+                .spacing(10)
+                .into()
+            }
+        };
+
+        let status = text(self.status.clone().unwrap_or_default()).size(12);
+
+        container(
+            container(column![header, display_content, status].spacing(20))
+                .width(Fill)
+                .padding(20)
+                .center_x(Fill),
+        )
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+    }
+}
@@ -288,8 +288,16 @@ fn stream() -> impl Sipper<Never, Event> {
 
         let _ = event_sender.send(Event::Connected(command_sender)).await;
 
-        // create a single simulator that we'll reuse across all renders
-        let mut simulator = simulator::Simulator::new();
+        // create a single simulator that we'll reuse across all renders,
+        // without blocking the runtime while the renderer starts
+        let mut simulator = match simulator::Simulator::try_new_async().await {
+            Ok(simulator) => simulator,
+            Err(e) => {
+                let _ = event_sender.send(Event::Error(e.to_string())).await;
+
+                return iced::futures::future::pending().await;
+            }
+        };
 
         loop {
             if let Some(command) = command_receiver.next().await {
@@ -297,7 +305,9 @@ fn stream() -> impl Sipper<Never, Event> {
                     Command::RenderSample => {
                         println!("Processing sample render request");
 
-                        let result = sample::render(&mut simulator);
+                        // Render on a thread of its own, keeping the runtime responsive
+                        let (returned, result) = simulator.run_async(sample::render).await;
+                        simulator = returned;
 
                         match result {
                             Ok(screenshot_data) => {
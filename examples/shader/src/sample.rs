@@ -1,9 +1,10 @@
 use iced::advanced::image::Bytes;
 use iced::widget::{column, container, row, text};
-use iced::{Element, Fill, Rectangle, Size};
+use iced::{Element, Fill, Size};
 use rand::Rng;
 
 use crate::simulator;
+use sleepy_hollow::export;
 
 #[derive(Debug, Clone)]
 pub struct PngScreenshot {
@@ -103,46 +104,19 @@ pub fn render(simulator: &mut simulator::Simulator) -> Result<PngScreenshot, Str
 
     println!("Rendering sample document");
 
-    // Take a screenshot with the element
+    // Take a screenshot of exactly the bounds of the document
     let scale_factor = 2.0;
-    let screenshot = simulator.screenshot(element, size, scale_factor)?;
-
-    // Account for the scale factor when cropping
-    let scale_factor = screenshot.scale_factor as f32;
-    let scaled_crop_rectangle = Rectangle {
-        x: 0,
-        y: 0,
-        width: (size.width as f32 * scale_factor) as u32,
-        height: (size.height as f32 * scale_factor) as u32,
-    };
+    let screenshot = simulator
+        .screenshot_content(element, size, 0, scale_factor)
+        .map_err(|e| e.to_string())?;
 
     println!(
-        "Scale factor: {}, Original crop: {:?}, Scaled crop: {:?}",
-        scale_factor, size, scaled_crop_rectangle
+        "Scale factor: {}, Max size: {:?}, Screenshot size: {:?}",
+        scale_factor, size, screenshot.size
     );
 
-    let screenshot = screenshot
-        .crop(scaled_crop_rectangle)
-        .map_err(|e| format!("Failed to crop screenshot: {:?}", e))?;
-
-    let mut png_data = Vec::new();
-    {
-        let mut encoder =
-            png::Encoder::new(&mut png_data, screenshot.size.width, screenshot.size.height);
-        encoder.set_color(png::ColorType::Rgba);
-
-        let mut writer = encoder
-            .write_header()
-            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
-
-        writer
-            .write_image_data(&screenshot.bytes.to_vec())
-            .map_err(|e| format!("Failed to write PNG data: {}", e))?;
-
-        writer
-            .finish()
-            .map_err(|e| format!("Failed to finish PNG encoding: {}", e))?;
-    }
+    let metadata = simulator.metadata(&screenshot);
+    let png_data = export::encode_png(&screenshot, Some(&metadata)).map_err(|e| e.to_string())?;
 
     // Return the PNG screenshot
     Ok(PngScreenshot {
@@ -0,0 +1,7 @@
+use sleepy_hollow::cli;
+use sleepy_hollow::scenario::Views;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    cli::run(&Views::new())
+}
@@ -0,0 +1,288 @@
+//! The `sleepy-hollow` command-line interface
+//!
+//! The bundled binary has no views registered, so it can only compare
+//! directories and play scenarios of registered views once a project wraps
+//! [`run`] in a binary of its own:
+//!
+//! ```no_run
+//! use sleepy_hollow::{cli, scenario::Views};
+//! # fn view<'a>() -> iced::Element<'a, ()> { iced::widget::text("Hello").into() }
+//!
+//! fn main() -> std::process::ExitCode {
+//!     cli::run(&Views::new().register("home", view))
+//! }
+//! ```
+use iced::Size;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use crate::export;
+use crate::regression::{self, Status, Summary};
+use crate::report;
+use crate::scenario::{self, Scenario, Views};
+use crate::simulator::{Background, Simulator};
+use crate::snapshot::Tolerance;
+
+const USAGE: &str = "\
+Usage:
+    sleepy-hollow render [--out DIR] [--size WxH] [--scale FACTOR] [--theme NAME]
+                         [--background theme|transparent|#RRGGBB[AA]]
+                         [--view NAME]... [SCENARIO.json]...
+    sleepy-hollow compare GOLDENS ACTUALS [--channel-tolerance N] [--pixel-tolerance N]
+                          [--min-ssim SSIM] [--diff DIR] [--report FILE]
+    sleepy-hollow views
+
+Commands:
+    render     Render registered views to OUT/<view>.png and the captures of
+               scenarios to OUT/<scenario>/<capture>.png
+    compare    Compare the PNGs of ACTUALS and its subdirectories against the
+               goldens with the same relative path in GOLDENS and exit with
+               status 1 on regressions, optionally writing an HTML report of
+               every case
+    views      List the registered views";
+
+// Appended to the usage when no views are registered, as in the bundled binary
+const NO_VIEWS: &str = "
+No views are registered in this binary, so `render` can only fail. Register
+views by calling `sleepy_hollow::cli::run` from a binary of your own.";
+
+/// Runs the command-line interface with the arguments of the process
+///
+/// Returns a success, `1` when `compare` finds regressions, or `2` when the
+/// command fails.
+pub fn run(views: &Views<'_>) -> ExitCode {
+    run_with(views, env::args().skip(1))
+}
+
+/// Runs the command-line interface with the given arguments, excluding the
+/// name of the binary
+pub fn run_with(views: &Views<'_>, arguments: impl IntoIterator<Item = String>) -> ExitCode {
+    let mut arguments = arguments.into_iter();
+
+    let result = match arguments.next().as_deref() {
+        Some("render") => render(views, arguments),
+        Some("compare") => compare(arguments),
+        Some("views") => {
+            for name in views.names() {
+                println!("{name}");
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Some("-h" | "--help" | "help") => {
+            println!("{}", usage(views));
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(command) => Err(format!("unknown command {command:?}")),
+        None => Err("no command given".to_owned()),
+    };
+
+    result.unwrap_or_else(|error| {
+        eprintln!("error: {error}\n\n{}", usage(views));
+
+        ExitCode::from(2)
+    })
+}
+
+fn usage(views: &Views<'_>) -> String {
+    if views.names().next().is_none() {
+        format!("{USAGE}\n{NO_VIEWS}")
+    } else {
+        USAGE.to_owned()
+    }
+}
+
+fn render(
+    views: &Views<'_>,
+    mut arguments: impl Iterator<Item = String>,
+) -> Result<ExitCode, String> {
+    let mut out = PathBuf::from(".");
+    let mut size = Size::new(1024.0, 768.0);
+    let mut scale_factor = 1.0;
+    let mut theme = None;
+    let mut background = Background::Theme;
+    let mut names = Vec::new();
+    let mut scenarios = Vec::new();
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--out" => out = value(&mut arguments, &argument)?,
+            "--size" => size = parse_size(&value::<String>(&mut arguments, &argument)?)?,
+            "--scale" => scale_factor = value(&mut arguments, &argument)?,
+            "--theme" => {
+                let name = value::<String>(&mut arguments, &argument)?;
+
+                theme = Some(scenario::theme(&name).map_err(|e| e.to_string())?);
+            }
+            "--background" => {
+                background = parse_background(&value::<String>(&mut arguments, &argument)?)?;
+            }
+            "--view" => names.push(value::<String>(&mut arguments, &argument)?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {flag:?}")),
+            _ => scenarios.push(PathBuf::from(argument)),
+        }
+    }
+
+    if names.is_empty() && scenarios.is_empty() {
+        return Err("nothing to render".to_owned());
+    }
+
+    let mut simulator = Simulator::<iced::Renderer>::try_new().map_err(|e| e.to_string())?;
+
+    if let Some(theme) = theme {
+        simulator.set_theme(theme);
+    }
+
+    simulator.set_background(background);
+
+    for name in names {
+        let element = views
+            .view(&name)
+            .ok_or_else(|| format!("no view registered as {name:?}"))?;
+
+        let screenshot = simulator
+            .screenshot(element, size, scale_factor)
+            .map_err(|e| format!("{name}: {e}"))?;
+
+        let path = out.join(format!("{name}.png"));
+
+        export::save(&screenshot, Some(&simulator.metadata(&screenshot)), &path)
+            .map_err(|e| e.to_string())?;
+
+        println!("{}", path.display());
+    }
+
+    for path in scenarios {
+        let scenario = Scenario::load(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+        let directory = out.join(stem(&path));
+
+        let captures = scenario
+            .run_to(&mut simulator, views, &directory)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+
+        for capture in captures {
+            println!("{}", capture.display());
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn compare(mut arguments: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let mut tolerance = Tolerance::default();
+    let mut diffs = None;
+    let mut report = None;
+    let mut directories = Vec::new();
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--channel-tolerance" => tolerance.channel = value(&mut arguments, &argument)?,
+            "--pixel-tolerance" => tolerance.pixels = value(&mut arguments, &argument)?,
+            "--min-ssim" => tolerance.min_ssim = Some(value(&mut arguments, &argument)?),
+            "--diff" => diffs = Some(value::<PathBuf>(&mut arguments, &argument)?),
+            "--report" => report = Some(value::<PathBuf>(&mut arguments, &argument)?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {flag:?}")),
+            _ => directories.push(PathBuf::from(argument)),
+        }
+    }
+
+    let [goldens, actuals] = <[PathBuf; 2]>::try_from(directories)
+        .map_err(|_| "expected a goldens and an actuals directory".to_owned())?;
+
+    let cases = regression::compare_directories(&goldens, &actuals, &tolerance)
+        .map_err(|e| e.to_string())?;
+
+    for case in &cases {
+        let label = match &case.status {
+            Status::Passed => "PASS",
+            Status::Failed | Status::SizeMismatch { .. } => "FAIL",
+            Status::Missing => "MISSING",
+            Status::New => "NEW",
+        };
+
+        print!("{label:<8} {}", case.name);
+
+        if let Status::SizeMismatch { expected, found } = &case.status {
+            print!(
+                "  size {}x{} != {}x{}",
+                expected.width, expected.height, found.width, found.height
+            );
+        }
+
+        if let (Some(diff), Some(metrics)) = (&case.diff, &case.metrics) {
+            print!(
+                "  {} pixels differ, max error {}, PSNR {:.2} dB, SSIM {:.4}",
+                diff.over_threshold, diff.max_error, metrics.psnr, metrics.ssim
+            );
+        }
+
+        println!();
+
+        let regression = case.diff.as_ref().filter(|_| case.status.is_regression());
+
+        if let (Some(directory), Some(diff)) = (&diffs, regression) {
+            export::save(&diff.heatmap, None, directory.join(&case.name))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let summary = Summary::new(&cases);
+
+    println!("\n{summary}");
+
+    if let Some(path) = report {
+        report::write(&actuals.display().to_string(), &cases, &path).map_err(|e| e.to_string())?;
+
+        println!("Report written to {}", path.display());
+    }
+
+    Ok(if summary.has_regressions() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn value<T>(arguments: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String>
+where
+    T: FromStr,
+{
+    let value = arguments
+        .next()
+        .ok_or_else(|| format!("missing value for {flag}"))?;
+
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {value:?} for {flag}"))
+}
+
+fn parse_size(size: &str) -> Result<Size, String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("invalid size {size:?}, expected WIDTHxHEIGHT"))?;
+
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) => Ok(Size::new(width, height)),
+        _ => Err(format!("invalid size {size:?}, expected WIDTHxHEIGHT")),
+    }
+}
+
+fn parse_background(background: &str) -> Result<Background, String> {
+    match background {
+        "theme" => Ok(Background::Theme),
+        "transparent" => Ok(Background::Transparent),
+        color => color.parse().map(Background::Color).map_err(|_| {
+            format!("invalid background {color:?}, expected theme, transparent or a hex color")
+        }),
+    }
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "scenario".to_owned())
+}
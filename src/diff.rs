@@ -0,0 +1,127 @@
+//! Quantify the differences between two screenshots
+use iced::window;
+
+use crate::Error;
+
+/// Per-pixel difference statistics between two screenshots
+#[derive(Debug, Clone)]
+pub struct Diff {
+    /// The largest difference found in any RGBA channel
+    pub max_error: u8,
+    /// The mean of the per-pixel errors
+    pub mean_error: f64,
+    /// The amount of pixels whose error exceeds the threshold
+    pub over_threshold: usize,
+    /// The threshold used to count differing pixels
+    pub threshold: u8,
+    /// A heatmap of the per-pixel errors, from black (equal) to white (maximum error)
+    pub heatmap: window::Screenshot,
+}
+
+impl Diff {
+    /// Returns the ratio of pixels whose error exceeds the threshold
+    pub fn over_threshold_ratio(&self) -> f64 {
+        let pixels = self.heatmap.size.width as usize * self.heatmap.size.height as usize;
+
+        if pixels == 0 {
+            0.0
+        } else {
+            self.over_threshold as f64 / pixels as f64
+        }
+    }
+}
+
+/// The screenshots of the same view taken with two backends and their [`Diff`]
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// The names of the backends, in rendering order
+    pub backends: (String, String),
+    /// The screenshots taken by each backend, in rendering order
+    pub screenshots: (window::Screenshot, window::Screenshot),
+    /// The difference between both screenshots
+    pub diff: Diff,
+}
+
+/// Computes the [`Diff`] between two screenshots of the same size
+///
+/// The error of a pixel is the largest difference found in any of its
+/// RGBA channels.
+pub fn diff(
+    expected: &window::Screenshot,
+    actual: &window::Screenshot,
+    threshold: u8,
+) -> Result<Diff, Error> {
+    if expected.size != actual.size {
+        return Err(Error::SizeMismatch {
+            expected: expected.size,
+            found: actual.size,
+        });
+    }
+
+    let mut max_error = 0;
+    let mut total_error = 0_u64;
+    let mut over_threshold = 0;
+    let mut heatmap = Vec::with_capacity(expected.bytes.len());
+
+    for error in errors(expected, actual) {
+        max_error = max_error.max(error);
+        total_error += u64::from(error);
+
+        if error > threshold {
+            over_threshold += 1;
+        }
+
+        heatmap.extend_from_slice(&heat(error));
+    }
+
+    let pixels = expected.bytes.len() / 4;
+
+    let mean_error = if pixels == 0 {
+        0.0
+    } else {
+        total_error as f64 / pixels as f64
+    };
+
+    Ok(Diff {
+        max_error,
+        mean_error,
+        over_threshold,
+        threshold,
+        heatmap: window::Screenshot::new(heatmap, expected.size, expected.scale_factor),
+    })
+}
+
+// Returns the error of every pixel: the largest difference found in any of
+// its RGBA channels
+pub(crate) fn errors<'a>(
+    expected: &'a window::Screenshot,
+    actual: &'a window::Screenshot,
+) -> impl Iterator<Item = u8> + 'a {
+    expected
+        .bytes
+        .chunks_exact(4)
+        .zip(actual.bytes.chunks_exact(4))
+        .map(|(e, a)| {
+            e.iter()
+                .zip(a)
+                .map(|(e, a)| e.abs_diff(*a))
+                .max()
+                .unwrap_or(0)
+        })
+}
+
+// Maps an error to a black-red-yellow-white color ramp
+pub(crate) fn heat(error: u8) -> [u8; 4] {
+    let t = f32::from(error) / 255.0;
+
+    let r = (t * 3.0).min(1.0);
+    let g = (t * 3.0 - 1.0).clamp(0.0, 1.0);
+    let b = (t * 3.0 - 2.0).clamp(0.0, 1.0);
+
+    [
+        (r * 255.0).round() as u8,
+        (g * 255.0).round() as u8,
+        (b * 255.0).round() as u8,
+        255,
+    ]
+}
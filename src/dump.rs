@@ -0,0 +1,638 @@
+//! Dump the layout tree of a user interface for structural assertions
+use iced::advanced::{Layout, widget};
+use iced::{Rectangle, Size};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::Error;
+use crate::simulator::{Kind, Match};
+
+/// The layout tree of a user interface, described by its widget operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dump {
+    /// The logical width of the viewport
+    pub width: f32,
+    /// The logical height of the viewport
+    pub height: f32,
+    /// The top-level widgets
+    pub nodes: Vec<Node>,
+}
+
+/// A widget in a [`Dump`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    /// The kind of the widget
+    pub kind: Kind,
+    /// The custom id of the widget, if any
+    ///
+    /// Ids generated by `iced` change from run to run, so they are omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The layout bounds of the widget
+    pub bounds: Bounds,
+    /// The text of the widget, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The children of the widget
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+/// The layout bounds of a [`Node`], in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    /// The horizontal position
+    pub x: f32,
+    /// The vertical position
+    pub y: f32,
+    /// The width
+    pub width: f32,
+    /// The height
+    pub height: f32,
+}
+
+impl Bounds {
+    fn approx_eq(&self, other: &Bounds, tolerance: f32) -> bool {
+        (self.x - other.x).abs() <= tolerance
+            && (self.y - other.y).abs() <= tolerance
+            && (self.width - other.width).abs() <= tolerance
+            && (self.height - other.height).abs() <= tolerance
+    }
+}
+
+impl From<Rectangle> for Bounds {
+    fn from(rectangle: Rectangle) -> Self {
+        Bounds {
+            x: rectangle.x,
+            y: rectangle.y,
+            width: rectangle.width,
+            height: rectangle.height,
+        }
+    }
+}
+
+impl From<Bounds> for Rectangle {
+    fn from(bounds: Bounds) -> Self {
+        Rectangle {
+            x: bounds.x,
+            y: bounds.y,
+            width: bounds.width,
+            height: bounds.height,
+        }
+    }
+}
+
+impl Dump {
+    /// Builds a [`Dump`] out of a layout tree and the widgets collected from it
+    ///
+    /// Every node of the layout becomes a [`Node`], and widgets are paired with
+    /// the nodes sharing their bounds, in traversal order. Nodes of widgets that
+    /// report nothing to widget operations are kept as [`Kind::Unknown`].
+    ///
+    /// Widgets outside of the layout, like overlays, are appended as
+    /// top-level nodes, as in [`Dump::from_matches`].
+    pub fn from_layout(
+        viewport: Size,
+        layout: Layout<'_>,
+        matches: impl IntoIterator<Item = Match>,
+    ) -> Self {
+        let mut bounds = Vec::new();
+        collect_bounds(layout, &mut bounds);
+
+        let (matches, mut rest): (Vec<_>, Vec<_>) = matches
+            .into_iter()
+            .partition(|widget| bounds.contains(&widget.bounds));
+
+        let mut matches = VecDeque::from(matches);
+        let root = from_layout(layout, &mut matches);
+
+        rest.extend(matches);
+
+        let mut dump = Dump::from_matches(viewport, rest);
+        dump.nodes.insert(0, root);
+
+        dump
+    }
+
+    /// Builds a [`Dump`] out of widgets collected in traversal order
+    ///
+    /// Each widget becomes a child of the closest preceding widget with a
+    /// smaller depth.
+    pub fn from_matches(viewport: Size, matches: impl IntoIterator<Item = Match>) -> Self {
+        // The ancestors of the current widget, with their depth
+        let mut stack: Vec<(usize, Node)> = Vec::new();
+        let mut nodes = Vec::new();
+
+        for widget in matches {
+            while stack
+                .last()
+                .is_some_and(|(depth, _)| *depth >= widget.depth)
+            {
+                let (_, node) = stack.pop().expect("Pop ancestor");
+                attach(&mut stack, &mut nodes, node);
+            }
+
+            stack.push((
+                widget.depth,
+                Node {
+                    kind: widget.kind,
+                    id: widget.id.as_ref().and_then(custom_id),
+                    bounds: widget.bounds.into(),
+                    text: widget.text,
+                    children: Vec::new(),
+                },
+            ));
+        }
+
+        while let Some((_, node)) = stack.pop() {
+            attach(&mut stack, &mut nodes, node);
+        }
+
+        Dump {
+            width: viewport.width,
+            height: viewport.height,
+            nodes,
+        }
+    }
+
+    /// Serializes the [`Dump`] as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Encode(e.to_string()))
+    }
+
+    /// Deserializes a [`Dump`] from JSON
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::Decode(e.to_string()))
+    }
+}
+
+// Returns the name of a custom id, which is only exposed through `Debug`
+fn custom_id(id: &widget::Id) -> Option<String> {
+    let debug = format!("{id:?}");
+    let quoted = debug.strip_prefix("Id(Custom(")?.strip_suffix("))")?;
+    let mut characters = quoted.strip_prefix('"')?.strip_suffix('"')?.chars();
+    let mut name = String::new();
+
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            name.push(character);
+            continue;
+        }
+
+        match characters.next()? {
+            'n' => name.push('\n'),
+            'r' => name.push('\r'),
+            't' => name.push('\t'),
+            '0' => name.push('\0'),
+            'u' => {
+                let code: String = characters
+                    .by_ref()
+                    .skip(1)
+                    .take_while(|character| *character != '}')
+                    .collect();
+
+                name.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            escaped => name.push(escaped),
+        }
+    }
+
+    (widget::Id::from(name.clone()) == *id).then_some(name)
+}
+
+fn collect_bounds(layout: Layout<'_>, bounds: &mut Vec<Rectangle>) {
+    bounds.push(layout.bounds());
+
+    for child in layout.children() {
+        collect_bounds(child, bounds);
+    }
+}
+
+fn from_layout(layout: Layout<'_>, matches: &mut VecDeque<Match>) -> Node {
+    let bounds = layout.bounds();
+
+    // Wrappers and their content may share bounds, like a `container` that
+    // shrinks to fit a `text_input`. A chain of such nodes is paired with as
+    // many widgets as possible, the deepest nodes first, so that a widget
+    // that does not report itself never takes the place of one that does.
+    let chain = std::iter::successors(Some(layout), |layout| {
+        layout
+            .children()
+            .next()
+            .filter(|child| child.bounds() == bounds)
+    })
+    .count();
+
+    let widgets = matches
+        .iter()
+        .take_while(|widget| widget.bounds == bounds)
+        .count();
+
+    let mut node = Node {
+        kind: Kind::Unknown,
+        id: None,
+        bounds: bounds.into(),
+        text: None,
+        children: Vec::new(),
+    };
+
+    // The deepest node of a chain takes every widget left over
+    let paired = if widgets >= chain {
+        widgets - chain + 1
+    } else {
+        0
+    };
+
+    for widget in matches.drain(..paired) {
+        if node.kind == Kind::Unknown || widget.kind.richness() > node.kind.richness() {
+            node.kind = widget.kind;
+        }
+
+        if node.id.is_none() {
+            node.id = widget.id.as_ref().and_then(custom_id);
+        }
+
+        if node.text.is_none() {
+            node.text = widget.text;
+        }
+    }
+
+    node.children = layout
+        .children()
+        .map(|child| from_layout(child, matches))
+        .collect();
+
+    node
+}
+
+fn attach(stack: &mut [(usize, Node)], nodes: &mut Vec<Node>, node: Node) {
+    match stack.last_mut() {
+        Some((_, parent)) => parent.children.push(node),
+        None => nodes.push(node),
+    }
+}
+
+/// A structural difference between two [`Dump`]s
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The viewport changed size
+    Viewport {
+        /// The old size
+        from: Size,
+        /// The new size
+        to: Size,
+    },
+    /// A widget only exists in the new dump
+    Added {
+        /// The path of the widget
+        path: String,
+        /// The widget
+        node: Node,
+    },
+    /// A widget only exists in the old dump
+    Removed {
+        /// The path of the widget
+        path: String,
+        /// The widget
+        node: Node,
+    },
+    /// A widget changed kind
+    Kind {
+        /// The path of the widget
+        path: String,
+        /// The old kind
+        from: Kind,
+        /// The new kind
+        to: Kind,
+    },
+    /// A widget changed bounds
+    Bounds {
+        /// The path of the widget
+        path: String,
+        /// The old bounds
+        from: Bounds,
+        /// The new bounds
+        to: Bounds,
+    },
+    /// A widget changed text
+    Text {
+        /// The path of the widget
+        path: String,
+        /// The old text
+        from: Option<String>,
+        /// The new text
+        to: Option<String>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Viewport { from, to } => write!(
+                f,
+                "viewport: {}x{} -> {}x{}",
+                from.width, from.height, to.width, to.height
+            ),
+            Change::Added { path, node } => write!(f, "{path}: added {:?}", node.kind),
+            Change::Removed { path, node } => write!(f, "{path}: removed {:?}", node.kind),
+            Change::Kind { path, from, to } => write!(f, "{path}: {from:?} -> {to:?}"),
+            Change::Bounds { path, from, to } => write!(
+                f,
+                "{path}: ({}, {}) {}x{} -> ({}, {}) {}x{}",
+                from.x, from.y, from.width, from.height, to.x, to.y, to.width, to.height
+            ),
+            Change::Text { path, from, to } => write!(f, "{path}: {from:?} -> {to:?}"),
+        }
+    }
+}
+
+/// Compares two [`Dump`]s widget by widget, ignoring bound changes within the tolerance
+///
+/// Widgets are paired by their position among their siblings, and their
+/// path is the list of those positions joined by `/`.
+pub fn diff(old: &Dump, new: &Dump, tolerance: f32) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    if (old.width, old.height) != (new.width, new.height) {
+        changes.push(Change::Viewport {
+            from: Size::new(old.width, old.height),
+            to: Size::new(new.width, new.height),
+        });
+    }
+
+    diff_children("", &old.nodes, &new.nodes, tolerance, &mut changes);
+
+    changes
+}
+
+fn diff_children(
+    parent: &str,
+    old: &[Node],
+    new: &[Node],
+    tolerance: f32,
+    changes: &mut Vec<Change>,
+) {
+    for index in 0..old.len().max(new.len()) {
+        let path = path(parent, index);
+
+        match (old.get(index), new.get(index)) {
+            (Some(old), Some(new)) => {
+                if old.kind != new.kind {
+                    changes.push(Change::Kind {
+                        path: path.clone(),
+                        from: old.kind,
+                        to: new.kind,
+                    });
+                }
+
+                if !old.bounds.approx_eq(&new.bounds, tolerance) {
+                    changes.push(Change::Bounds {
+                        path: path.clone(),
+                        from: old.bounds,
+                        to: new.bounds,
+                    });
+                }
+
+                if old.text != new.text {
+                    changes.push(Change::Text {
+                        path: path.clone(),
+                        from: old.text.clone(),
+                        to: new.text.clone(),
+                    });
+                }
+
+                diff_children(&path, &old.children, &new.children, tolerance, changes);
+            }
+            (Some(old), None) => changes.push(Change::Removed {
+                path,
+                node: old.clone(),
+            }),
+            (None, Some(new)) => changes.push(Change::Added {
+                path,
+                node: new.clone(),
+            }),
+            (None, None) => {}
+        }
+    }
+}
+
+// Returns the path of the child at the given index of the widget at `parent`
+pub(crate) fn path(parent: &str, index: usize) -> String {
+    if parent.is_empty() {
+        index.to_string()
+    } else {
+        format!("{parent}/{index}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::Point;
+    use iced::advanced::layout;
+
+    fn widget(depth: usize, kind: Kind, text: &str) -> Match {
+        Match {
+            kind,
+            id: None,
+            bounds: Rectangle::new(Point::ORIGIN, Size::new(10.0, 10.0)),
+            text: Some(text.to_owned()),
+            depth,
+        }
+    }
+
+    fn texts(nodes: &[Node]) -> Vec<&str> {
+        nodes
+            .iter()
+            .map(|node| node.text.as_deref().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn builds_the_tree_from_depths() {
+        let dump = Dump::from_matches(
+            Size::new(100.0, 100.0),
+            [
+                widget(0, Kind::Container, "root"),
+                widget(1, Kind::Container, "a"),
+                widget(2, Kind::Text, "a1"),
+                widget(2, Kind::Text, "a2"),
+                widget(1, Kind::Container, "b"),
+                widget(2, Kind::Container, "b1"),
+                widget(3, Kind::Text, "b1x"),
+                widget(0, Kind::Text, "second root"),
+            ],
+        );
+
+        assert_eq!(texts(&dump.nodes), ["root", "second root"]);
+
+        let root = &dump.nodes[0];
+        assert_eq!(texts(&root.children), ["a", "b"]);
+        assert_eq!(texts(&root.children[0].children), ["a1", "a2"]);
+        assert_eq!(texts(&root.children[1].children), ["b1"]);
+        assert_eq!(texts(&root.children[1].children[0].children), ["b1x"]);
+        assert!(dump.nodes[1].children.is_empty());
+    }
+
+    fn node(x: f32, y: f32, width: f32, height: f32, children: Vec<layout::Node>) -> layout::Node {
+        layout::Node::with_children(Size::new(width, height), children).move_to(Point::new(x, y))
+    }
+
+    fn at(mut widget: Match, x: f32, y: f32, width: f32, height: f32) -> Match {
+        widget.bounds = Rectangle::new(Point::new(x, y), Size::new(width, height));
+        widget
+    }
+
+    #[test]
+    fn keeps_layout_nodes_without_widgets() {
+        let root = node(
+            0.0,
+            0.0,
+            100.0,
+            20.0,
+            vec![
+                node(0.0, 0.0, 50.0, 20.0, Vec::new()),
+                node(50.0, 0.0, 50.0, 20.0, Vec::new()),
+            ],
+        );
+
+        let dump = Dump::from_layout(
+            Size::new(100.0, 100.0),
+            Layout::new(&root),
+            [
+                at(widget(0, Kind::Container, "row"), 0.0, 0.0, 100.0, 20.0),
+                at(widget(1, Kind::Text, "a"), 50.0, 0.0, 50.0, 20.0),
+            ],
+        );
+
+        let kinds: Vec<_> = dump.nodes[0].children.iter().map(|n| n.kind).collect();
+
+        assert_eq!(dump.nodes.len(), 1);
+        assert_eq!(dump.nodes[0].kind, Kind::Container);
+        assert_eq!(kinds, [Kind::Unknown, Kind::Text]);
+        assert_eq!(dump.nodes[0].children[1].text.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn pairs_wrapped_widgets_with_the_deepest_node() {
+        let root = node(
+            0.0,
+            0.0,
+            100.0,
+            20.0,
+            vec![node(
+                0.0,
+                0.0,
+                100.0,
+                20.0,
+                vec![node(5.0, 5.0, 90.0, 10.0, Vec::new())],
+            )],
+        );
+
+        let text_input = at(widget(0, Kind::TextInput, "name"), 0.0, 0.0, 100.0, 20.0);
+        let dump = Dump::from_layout(Size::new(100.0, 100.0), Layout::new(&root), [text_input]);
+
+        let wrapper = &dump.nodes[0];
+        let input = &wrapper.children[0];
+
+        assert_eq!(wrapper.kind, Kind::Unknown);
+        assert_eq!(input.kind, Kind::TextInput);
+        assert_eq!(input.text.as_deref(), Some("name"));
+        assert_eq!(input.children[0].kind, Kind::Unknown);
+    }
+
+    #[test]
+    fn appends_widgets_outside_the_layout() {
+        let root = node(0.0, 0.0, 100.0, 20.0, Vec::new());
+
+        let dump = Dump::from_layout(
+            Size::new(100.0, 100.0),
+            Layout::new(&root),
+            [
+                at(widget(0, Kind::Text, "base"), 0.0, 0.0, 100.0, 20.0),
+                at(widget(0, Kind::Text, "overlay"), 0.0, 20.0, 100.0, 50.0),
+            ],
+        );
+
+        assert_eq!(texts(&dump.nodes), ["base", "overlay"]);
+    }
+
+    // `custom_id` relies on the `Debug` output of `widget::Id`, which may
+    // change with any release of `iced`
+    #[test]
+    fn reads_custom_ids_from_the_pinned_iced() {
+        assert_eq!(custom_id(&widget::Id::new("name")).as_deref(), Some("name"));
+        assert_eq!(
+            custom_id(&widget::Id::new("ünïcode – \\ \n")).as_deref(),
+            Some("ünïcode – \\ \n")
+        );
+        assert_eq!(custom_id(&widget::Id::unique()), None);
+    }
+
+    #[test]
+    fn serializes_only_custom_ids() {
+        let mut custom = widget(0, Kind::TextInput, "");
+        custom.id = Some(widget::Id::new("name \"quoted\""));
+
+        let mut unique = widget(0, Kind::TextInput, "");
+        unique.id = Some(widget::Id::unique());
+
+        let dump = Dump::from_matches(Size::new(100.0, 100.0), [custom, unique]);
+
+        assert_eq!(dump.nodes[0].id.as_deref(), Some("name \"quoted\""));
+        assert_eq!(dump.nodes[1].id, None);
+    }
+
+    #[test]
+    fn diffs_by_path() {
+        let old = Dump::from_matches(
+            Size::new(100.0, 100.0),
+            [
+                widget(0, Kind::Container, "root"),
+                widget(1, Kind::Text, "a"),
+                widget(1, Kind::Text, "b"),
+            ],
+        );
+
+        let mut moved = widget(1, Kind::Text, "a");
+        moved.bounds.y = 20.0;
+
+        let new = Dump::from_matches(
+            Size::new(200.0, 100.0),
+            [
+                widget(0, Kind::Container, "root"),
+                moved,
+                widget(1, Kind::Container, "b"),
+                widget(2, Kind::Text, "c"),
+            ],
+        );
+
+        let changes: Vec<_> = diff(&old, &new, 1.0)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            changes,
+            [
+                "viewport: 100x100 -> 200x100",
+                "0/0: (0, 0) 10x10 -> (0, 20) 10x10",
+                "0/1: Text -> Container",
+                "0/1/0: added Text",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_bounds_within_the_tolerance() {
+        let old = Dump::from_matches(Size::new(100.0, 100.0), [widget(0, Kind::Text, "a")]);
+
+        let mut nudged = widget(0, Kind::Text, "a");
+        nudged.bounds.x = 0.5;
+
+        let new = Dump::from_matches(Size::new(100.0, 100.0), [nudged]);
+
+        assert_eq!(diff(&old, &new, 1.0), Vec::new());
+    }
+}
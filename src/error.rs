@@ -0,0 +1,139 @@
+//! Report which step of a simulation failed
+use iced::Size;
+use iced::window::screenshot::CropError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::simulator::Selector;
+
+/// An error that occurred while simulating a user interface
+#[derive(Debug)]
+pub enum Error {
+    /// The headless renderer could not be created
+    RendererCreation {
+        /// The backend that was requested, if any
+        backend: Option<String>,
+    },
+    /// A font could not be loaded into the font system
+    FontLoading(String),
+    /// None of the candidates for the default font family is loaded
+    MissingFont(Vec<String>),
+    /// The user interface could not be laid out in the given viewport
+    Layout {
+        /// The logical size of the viewport
+        size: Size,
+        /// The scale factor of the viewport
+        scale_factor: f32,
+    },
+    /// An element could not be measured because it fills an unbounded space
+    Measure {
+        /// The maximum size the element was laid out in
+        max: Size,
+        /// The size the element tried to fill
+        size: Size,
+    },
+    /// The renderer produced a buffer that does not match the viewport
+    Draw {
+        /// The expected length of the RGBA buffer
+        expected: usize,
+        /// The actual length of the RGBA buffer
+        found: usize,
+    },
+    /// Two screenshots that must be compared have different sizes
+    SizeMismatch {
+        /// The size of the expected screenshot
+        expected: Size<u32>,
+        /// The size of the actual screenshot
+        found: Size<u32>,
+    },
+    /// No widget was matched by a selector
+    NotFound(Selector),
+    /// A screenshot could not be cropped
+    Crop(CropError),
+    /// An image could not be encoded
+    Encode(String),
+    /// An image could not be decoded
+    Decode(String),
+    /// The executor used to run tasks could not be created
+    Executor(io::Error),
+    /// A worker of a rendering pool stopped before finishing a job
+    WorkerStopped,
+    /// A scenario could not be parsed
+    Scenario(String),
+    /// No view was registered with the given name
+    UnknownView(String),
+    /// No built-in theme has the given name
+    UnknownTheme(String),
+    /// A file could not be read or written
+    Io {
+        /// The path of the file
+        path: PathBuf,
+        /// The underlying I/O error
+        error: io::Error,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RendererCreation { backend: None } => {
+                write!(f, "Failed to create headless renderer")
+            }
+            Error::RendererCreation {
+                backend: Some(backend),
+            } => write!(
+                f,
+                "Failed to create headless renderer with backend {backend:?}"
+            ),
+            Error::FontLoading(error) => write!(f, "Failed to load font: {error}"),
+            Error::MissingFont(families) => {
+                write!(f, "No font loaded for any of the families {families:?}")
+            }
+            Error::Layout { size, scale_factor } => write!(
+                f,
+                "Failed to lay out user interface of size {}x{} at scale factor {scale_factor}",
+                size.width, size.height
+            ),
+            Error::Measure { max, size } => write!(
+                f,
+                "Failed to measure element within {}x{}: it fills {}x{}",
+                max.width, max.height, size.width, size.height
+            ),
+            Error::Draw { expected, found } => write!(
+                f,
+                "Failed to draw user interface: expected {expected} bytes, found {found}"
+            ),
+            Error::SizeMismatch { expected, found } => write!(
+                f,
+                "Screenshot sizes differ: expected {}x{}, found {}x{}",
+                expected.width, expected.height, found.width, found.height
+            ),
+            Error::NotFound(selector) => write!(f, "No widget found for {selector:?}"),
+            Error::Crop(error) => write!(f, "Failed to crop screenshot: {error:?}"),
+            Error::Encode(error) => write!(f, "Failed to encode image: {error}"),
+            Error::Decode(error) => write!(f, "Failed to decode image: {error}"),
+            Error::Executor(error) => write!(f, "Failed to create executor: {error}"),
+            Error::WorkerStopped => write!(f, "Failed to render job: worker stopped"),
+            Error::Scenario(error) => write!(f, "Failed to parse scenario: {error}"),
+            Error::UnknownView(name) => write!(f, "No view registered as {name:?}"),
+            Error::UnknownTheme(name) => write!(f, "No theme named {name:?}"),
+            Error::Io { path, error } => write!(f, "Failed to access {path:?}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Executor(error) | Error::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<CropError> for Error {
+    fn from(error: CropError) -> Self {
+        Error::Crop(error)
+    }
+}
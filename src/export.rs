@@ -0,0 +1,409 @@
+//! Export screenshots as self-describing image files
+//!
+//! PNG files embed the [`Metadata`] of the render in `tEXt` chunks, which
+//! [`load`] recovers. QOI and raw RGBA files only contain pixels.
+use iced::{Size, window};
+use std::fs;
+use std::path::Path;
+
+use crate::Error;
+
+const WIDTH: &str = "Width";
+const HEIGHT: &str = "Height";
+const SCALE_FACTOR: &str = "Scale Factor";
+const BACKEND: &str = "Backend";
+const THEME: &str = "Theme";
+const FONTS: &str = "Fonts";
+const SOFTWARE: &str = "Software";
+
+/// The conditions a screenshot was rendered under
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// The physical size of the screenshot
+    pub size: Size<u32>,
+    /// The scale factor the screenshot was rendered with
+    pub scale_factor: f64,
+    /// The name of the renderer backend
+    pub backend: Option<String>,
+    /// The name of the theme
+    pub theme: Option<String>,
+    /// The font families in use, starting with the default one
+    pub fonts: Vec<String>,
+}
+
+impl Metadata {
+    /// Creates [`Metadata`] describing only the size and scale factor of the screenshot
+    pub fn new(screenshot: &window::Screenshot) -> Self {
+        Self {
+            size: screenshot.size,
+            scale_factor: screenshot.scale_factor,
+            backend: None,
+            theme: None,
+            fonts: Vec::new(),
+        }
+    }
+
+    fn to_text(&self) -> Vec<(&'static str, String)> {
+        let mut text = vec![
+            (
+                SOFTWARE,
+                format!("sleepy_hollow {}", env!("CARGO_PKG_VERSION")),
+            ),
+            (WIDTH, self.size.width.to_string()),
+            (HEIGHT, self.size.height.to_string()),
+            (SCALE_FACTOR, self.scale_factor.to_string()),
+        ];
+
+        if let Some(backend) = &self.backend {
+            text.push((BACKEND, backend.clone()));
+        }
+
+        if let Some(theme) = &self.theme {
+            text.push((THEME, theme.clone()));
+        }
+
+        if !self.fonts.is_empty() {
+            text.push((FONTS, self.fonts.join("\n")));
+        }
+
+        text
+    }
+
+    fn from_text<'a>(text: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut width = None;
+        let mut height = None;
+        let mut scale_factor = None;
+        let mut backend = None;
+        let mut theme = None;
+        let mut fonts = Vec::new();
+
+        for (keyword, value) in text {
+            match keyword {
+                WIDTH => width = value.parse().ok(),
+                HEIGHT => height = value.parse().ok(),
+                SCALE_FACTOR => scale_factor = value.parse().ok(),
+                BACKEND => backend = Some(value.to_owned()),
+                THEME => theme = Some(value.to_owned()),
+                FONTS => fonts = value.lines().map(str::to_owned).collect(),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            size: Size::new(width?, height?),
+            scale_factor: scale_factor?,
+            backend,
+            theme,
+            fonts,
+        })
+    }
+}
+
+/// The file format of an exported screenshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A PNG image with embedded [`Metadata`]
+    Png,
+    /// A QOI image
+    Qoi,
+    /// Raw RGBA pixels, without any header
+    Raw,
+}
+
+impl Format {
+    /// Guesses the [`Format`] from the extension of a path
+    ///
+    /// `.png`, `.qoi`, `.rgba` and `.raw` are recognized.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "png" => Some(Format::Png),
+            "qoi" => Some(Format::Qoi),
+            "rgba" | "raw" => Some(Format::Raw),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes the screenshot in the given [`Format`]
+///
+/// The metadata is only embedded in [`Format::Png`].
+pub fn encode(
+    screenshot: &window::Screenshot,
+    metadata: Option<&Metadata>,
+    format: Format,
+) -> Result<Vec<u8>, Error> {
+    match format {
+        Format::Png => encode_png(screenshot, metadata),
+        Format::Qoi => encode_qoi(screenshot),
+        Format::Raw => Ok(screenshot.bytes.to_vec()),
+    }
+}
+
+/// Writes the screenshot to the given path, choosing the [`Format`] from its extension
+pub fn save(
+    screenshot: &window::Screenshot,
+    metadata: Option<&Metadata>,
+    path: impl AsRef<Path>,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+
+    let format = Format::from_path(path)
+        .ok_or_else(|| Error::Encode(format!("unknown image format for {path:?}")))?;
+
+    let bytes = encode(screenshot, metadata, format)?;
+
+    write_file(path, bytes)
+}
+
+// Writes the file, creating its parent directories first
+pub(crate) fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|error| Error::Io {
+            path: parent.to_path_buf(),
+            error,
+        })?;
+    }
+
+    fs::write(path, contents).map_err(|error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Reads a PNG or QOI file, along with the [`Metadata`] embedded in it, if any
+pub fn load(path: impl AsRef<Path>) -> Result<(window::Screenshot, Option<Metadata>), Error> {
+    let path = path.as_ref();
+
+    let bytes = fs::read(path).map_err(|error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    match Format::from_path(path) {
+        Some(Format::Qoi) => Ok((decode_qoi(&bytes)?, None)),
+        Some(Format::Png) | None => decode_png(&bytes),
+        Some(Format::Raw) => Err(Error::Decode(format!(
+            "raw RGBA file {path:?} has no size information"
+        ))),
+    }
+}
+
+/// Reads the [`Metadata`] embedded in a PNG file, if any
+pub fn read_metadata(path: impl AsRef<Path>) -> Result<Option<Metadata>, Error> {
+    load(path).map(|(_, metadata)| metadata)
+}
+
+/// Encodes the screenshot as a PNG, embedding the given [`Metadata`]
+///
+/// The metadata is written as UTF-8 `iTXt` chunks, since theme and font
+/// names are not limited to Latin-1.
+pub fn encode_png(
+    screenshot: &window::Screenshot,
+    metadata: Option<&Metadata>,
+) -> Result<Vec<u8>, Error> {
+    let mut png_data = Vec::new();
+    {
+        let mut encoder =
+            png::Encoder::new(&mut png_data, screenshot.size.width, screenshot.size.height);
+        encoder.set_color(png::ColorType::Rgba);
+
+        for (keyword, text) in metadata.map(Metadata::to_text).unwrap_or_default() {
+            encoder
+                .add_itxt_chunk(keyword.to_owned(), text)
+                .map_err(|e| Error::Encode(e.to_string()))?;
+        }
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| Error::Encode(e.to_string()))?;
+
+        writer
+            .write_image_data(&screenshot.bytes)
+            .map_err(|e| Error::Encode(e.to_string()))?;
+
+        writer.finish().map_err(|e| Error::Encode(e.to_string()))?;
+    }
+
+    Ok(png_data)
+}
+
+/// Decodes a PNG, recovering the [`Metadata`] embedded in it, if any
+///
+/// The scale factor of the screenshot is taken from the metadata, or 1 if
+/// there is none.
+pub fn decode_png(bytes: &[u8]) -> Result<(window::Screenshot, Option<Metadata>), Error> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| Error::Decode(e.to_string()))?;
+
+    let info = reader.info();
+
+    // Older screenshots were written with Latin-1 `tEXt` chunks
+    let mut text: Vec<_> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+
+    for chunk in &info.utf8_text {
+        let value = chunk.get_text().map_err(|e| Error::Decode(e.to_string()))?;
+
+        text.push((chunk.keyword.clone(), value));
+    }
+
+    let metadata = Metadata::from_text(
+        text.iter()
+            .map(|(keyword, value)| (keyword.as_str(), value.as_str())),
+    );
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| Error::Decode(e.to_string()))?;
+
+    buffer.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(Error::Decode(
+                "indexed PNG could not be expanded".to_owned(),
+            ));
+        }
+    };
+
+    let scale_factor = metadata
+        .as_ref()
+        .map_or(1.0, |metadata| metadata.scale_factor);
+
+    Ok((
+        window::Screenshot::new(rgba, Size::new(info.width, info.height), scale_factor),
+        metadata,
+    ))
+}
+
+/// Encodes the screenshot as a QOI image
+pub fn encode_qoi(screenshot: &window::Screenshot) -> Result<Vec<u8>, Error> {
+    qoi::encode_to_vec(
+        &screenshot.bytes,
+        screenshot.size.width,
+        screenshot.size.height,
+    )
+    .map_err(|e| Error::Encode(e.to_string()))
+}
+
+/// Decodes a QOI image with a scale factor of 1
+pub fn decode_qoi(bytes: &[u8]) -> Result<window::Screenshot, Error> {
+    let mut decoder = qoi::Decoder::new(bytes)
+        .map_err(|e| Error::Decode(e.to_string()))?
+        .with_channels(qoi::Channels::Rgba);
+
+    let size = Size::new(decoder.header().width, decoder.header().height);
+
+    let rgba = decoder
+        .decode_to_vec()
+        .map_err(|e| Error::Decode(e.to_string()))?;
+
+    Ok(window::Screenshot::new(rgba, size, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screenshot() -> window::Screenshot {
+        let bytes: Vec<u8> = (0..3 * 2 * 4).map(|i| (i * 10) as u8).collect();
+
+        window::Screenshot::new(bytes, Size::new(3, 2), 1.5)
+    }
+
+    #[test]
+    fn png_round_trips_pixels_and_metadata() {
+        let screenshot = screenshot();
+
+        let metadata = Metadata {
+            backend: Some("tiny-skia".to_owned()),
+            theme: Some("Tokyo Night".to_owned()),
+            fonts: vec!["Fira Sans".to_owned(), "Noto Color Emoji".to_owned()],
+            ..Metadata::new(&screenshot)
+        };
+
+        assert!(
+            metadata
+                .to_text()
+                .contains(&(FONTS, "Fira Sans\nNoto Color Emoji".to_owned()))
+        );
+
+        let png = encode_png(&screenshot, Some(&metadata)).unwrap();
+        let (decoded, decoded_metadata) = decode_png(&png).unwrap();
+
+        assert_eq!(decoded.bytes, screenshot.bytes);
+        assert_eq!(decoded.size, Size::new(3, 2));
+        assert_eq!(decoded.scale_factor, screenshot.scale_factor);
+        assert_eq!(decoded_metadata, Some(metadata));
+    }
+
+    #[test]
+    fn png_round_trips_names_outside_of_latin1() {
+        let screenshot = screenshot();
+
+        let metadata = Metadata {
+            theme: Some("Solarized – Dark".to_owned()),
+            fonts: vec!["思源黑体".to_owned()],
+            ..Metadata::new(&screenshot)
+        };
+
+        let png = encode_png(&screenshot, Some(&metadata)).unwrap();
+        let (_, decoded_metadata) = decode_png(&png).unwrap();
+
+        assert_eq!(decoded_metadata, Some(metadata));
+    }
+
+    #[test]
+    fn png_with_latin1_metadata_is_decoded() {
+        let screenshot = screenshot();
+        let metadata = Metadata::new(&screenshot);
+
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_data, 3, 2);
+            encoder.set_color(png::ColorType::Rgba);
+
+            for (keyword, text) in metadata.to_text() {
+                encoder.add_text_chunk(keyword.to_owned(), text).unwrap();
+            }
+
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&screenshot.bytes).unwrap();
+        }
+
+        let (_, decoded_metadata) = decode_png(&png_data).unwrap();
+
+        assert_eq!(decoded_metadata, Some(metadata));
+    }
+
+    #[test]
+    fn png_without_metadata_has_a_scale_factor_of_one() {
+        let png = encode_png(&screenshot(), None).unwrap();
+        let (decoded, metadata) = decode_png(&png).unwrap();
+
+        assert_eq!(decoded.bytes, screenshot().bytes);
+        assert_eq!(decoded.scale_factor, 1.0);
+        assert_eq!(metadata, None);
+    }
+}
@@ -0,0 +1,292 @@
+//! Load fonts into the global font system and find the glyphs they lack
+//!
+//! Fonts are shared by every [`Simulator`](crate::simulator::Simulator) of
+//! the process. Text shaped with [`Shaping::Advanced`] falls back to any
+//! loaded font that has a missing glyph, so loading the right fonts is enough
+//! to render mixed scripts and emoji; characters that no loaded font covers
+//! are drawn as tofu and can be found with
+//! [`Simulator::missing_glyphs`](crate::simulator::Simulator::missing_glyphs).
+//!
+//! [`Shaping::Advanced`]: iced::widget::text::Shaping::Advanced
+use iced::advanced::graphics::text::{Paragraph, font_system};
+use iced::advanced::widget::{self, tree};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::Error;
+
+/// The family used instead of [`iced::Font::DEFAULT`] when no candidates
+/// are configured; see [`Fonts::default_family_candidates`]
+pub const DEFAULT_FAMILY: &str = "Fira Sans";
+
+// The extensions of the font files loaded from directories
+const EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+// The files loaded by any `Fonts`, so that the simulators of a pool share them
+// instead of loading every face into the global font system again
+static LOADED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// The fonts to load when creating a [`Simulator`](crate::simulator::Simulator)
+///
+/// Each file is loaded once per process, however many simulators use it.
+#[derive(Debug, Clone, Default)]
+pub struct Fonts {
+    files: Vec<PathBuf>,
+    directories: Vec<PathBuf>,
+    default_family_candidates: Vec<&'static str>,
+}
+
+impl Fonts {
+    /// Creates an empty set of [`Fonts`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a font file to load
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Adds a directory whose font files are loaded, recursively
+    pub fn directory(mut self, path: impl Into<PathBuf>) -> Self {
+        self.directories.push(path.into());
+        self
+    }
+
+    /// Sets the families to pick the default font from, in order of preference
+    ///
+    /// The first of them that is loaded replaces [`iced::Font::DEFAULT`]; the
+    /// others are not used afterwards. Missing glyphs fall back to any loaded
+    /// font instead, as explained in the [module documentation](self).
+    /// Creating the simulator fails if none of them is loaded.
+    pub fn default_family_candidates(
+        mut self,
+        families: impl IntoIterator<Item = &'static str>,
+    ) -> Self {
+        self.default_family_candidates = families.into_iter().collect();
+        self
+    }
+
+    // Loads every file and directory that was not loaded yet by this process
+    pub(crate) fn load(&self) -> Result<(), Error> {
+        let mut paths = self.files.clone();
+
+        for path in &self.directories {
+            font_files(path, &mut paths)?;
+        }
+
+        // Held while loading, so concurrent simulators wait for the fonts
+        // instead of loading them twice
+        let mut loaded = LOADED
+            .lock()
+            .map_err(|error| Error::FontLoading(error.to_string()))?;
+
+        for path in paths {
+            let path = fs::canonicalize(&path).unwrap_or(path);
+
+            if loaded.contains(&path) {
+                continue;
+            }
+
+            load_file(&path)?;
+
+            let _ = loaded.insert(path);
+        }
+
+        Ok(())
+    }
+
+    // Resolves the default font of a simulator
+    pub(crate) fn default_font(&self, font: iced::Font) -> Result<iced::Font, Error> {
+        if font != iced::Font::DEFAULT {
+            return Ok(font);
+        }
+
+        if self.default_family_candidates.is_empty() {
+            return Ok(iced::Font::with_name(DEFAULT_FAMILY));
+        }
+
+        let loaded = families()?;
+
+        self.default_family_candidates
+            .iter()
+            .copied()
+            .find(|family| loaded.contains(*family))
+            .map(iced::Font::with_name)
+            .ok_or_else(|| {
+                Error::MissingFont(
+                    self.default_family_candidates
+                        .iter()
+                        .map(|&family| family.to_owned())
+                        .collect(),
+                )
+            })
+    }
+}
+
+/// A character that none of the loaded fonts can draw
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingGlyph {
+    /// The missing character
+    pub character: char,
+    /// The content of the text that contains it
+    pub text: String,
+}
+
+impl fmt::Display for MissingGlyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "missing glyph for {:?} (U+{:04X}) in {:?}",
+            self.character, self.character as u32, self.text
+        )
+    }
+}
+
+/// Loads a font from its bytes
+///
+/// Fails if the bytes do not contain any font face.
+pub fn load(font: impl Into<Cow<'static, [u8]>>) -> Result<(), Error> {
+    let mut font_system = font_system()
+        .write()
+        .map_err(|error| Error::FontLoading(error.to_string()))?;
+
+    let faces = font_system.raw().db().len();
+
+    font_system.load_font(font.into());
+
+    if font_system.raw().db().len() == faces {
+        return Err(Error::FontLoading("no font face found".to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Loads a font file
+pub fn load_file(path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+
+    let bytes = fs::read(path).map_err(|error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    load(bytes).map_err(|error| match error {
+        Error::FontLoading(error) => Error::FontLoading(format!("{}: {error}", path.display())),
+        error => error,
+    })
+}
+
+/// Loads every font file in a directory and its subdirectories, returning
+/// the amount of loaded files
+pub fn load_directory(path: impl AsRef<Path>) -> Result<usize, Error> {
+    let mut paths = Vec::new();
+
+    font_files(path.as_ref(), &mut paths)?;
+
+    for path in &paths {
+        load_file(path)?;
+    }
+
+    Ok(paths.len())
+}
+
+// Collects the font files in a directory and its subdirectories
+fn font_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries = fs::read_dir(path).map_err(|error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    // Sorted, so fonts are always loaded in the same order
+    let mut paths = BTreeSet::new();
+
+    for entry in entries {
+        let entry = entry.map_err(|error| Error::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        let _ = paths.insert(entry.path());
+    }
+
+    for path in paths {
+        if path.is_dir() {
+            font_files(&path, files)?;
+        } else if is_font(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the names of the font families loaded in the font system, sorted
+///
+/// System fonts are included.
+pub fn families() -> Result<BTreeSet<String>, Error> {
+    let mut font_system = font_system()
+        .write()
+        .map_err(|error| Error::FontLoading(error.to_string()))?;
+
+    Ok(font_system
+        .raw()
+        .db()
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(family, _)| family.clone()))
+        .collect())
+}
+
+// Finds the characters drawn as tofu by every `text` widget
+pub(crate) fn find_missing(tree: &widget::Tree, missing: &mut Vec<MissingGlyph>) {
+    if tree.tag == tree::Tag::of::<widget::text::State<Paragraph>>() {
+        let paragraph = tree.state.downcast_ref::<widget::text::State<Paragraph>>();
+        let text = paragraph.content();
+        let raw = paragraph.raw();
+
+        for run in raw.buffer().layout_runs() {
+            for glyph in run.glyphs.iter().filter(|glyph| glyph.glyph_id == 0) {
+                for character in run.text[glyph.start..glyph.end].chars() {
+                    let glyph = MissingGlyph {
+                        character,
+                        text: text.to_owned(),
+                    };
+
+                    if !character.is_whitespace() && !missing.contains(&glyph) {
+                        missing.push(glyph);
+                    }
+                }
+            }
+        }
+    }
+
+    for child in &tree.children {
+        find_missing(child, missing);
+    }
+}
+
+fn is_font(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        EXTENSIONS
+            .iter()
+            .any(|font| extension.eq_ignore_ascii_case(font))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_without_faces_are_rejected() {
+        assert!(matches!(
+            load(b"not a font".as_slice()),
+            Err(Error::FontLoading(_))
+        ));
+    }
+}
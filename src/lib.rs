@@ -1 +1,18 @@
+pub mod cli;
+pub mod diff;
+pub mod dump;
+pub mod error;
+pub mod export;
+pub mod font;
+pub mod lint;
+pub mod matrix;
+pub mod metrics;
+pub mod pool;
+pub mod recording;
+pub mod regression;
+pub mod report;
+pub mod scenario;
 pub mod simulator;
+pub mod snapshot;
+
+pub use error::Error;
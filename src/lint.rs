@@ -0,0 +1,250 @@
+//! Check laid out user interfaces for common layout mistakes
+//!
+//! Widgets are checked against the [`Dump`] of their layout tree, so every
+//! laid out widget is considered, even the ones that report nothing to
+//! widget operations. Children of a `scrollable` are allowed to extend
+//! outside of it.
+use iced::advanced::Layout;
+use iced::advanced::text::Paragraph;
+use iced::advanced::widget::{self, tree};
+use iced::{Rectangle, Size};
+use std::fmt;
+
+use crate::dump::{self, Dump, Node};
+use crate::simulator::Kind;
+
+/// The checks to run on a user interface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checks {
+    /// Whether to flag sibling widgets with overlapping bounds
+    ///
+    /// Disable it for interfaces that layer widgets on purpose, like a `stack`.
+    pub overlaps: bool,
+    /// Whether to flag widgets extending outside their parent or the viewport
+    pub out_of_bounds: bool,
+    /// Whether to flag text that needs more space than it was given
+    pub clipped_text: bool,
+    /// The distance, in logical pixels, that bounds may be off by before being flagged
+    pub tolerance: f32,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self {
+            overlaps: true,
+            out_of_bounds: true,
+            clipped_text: true,
+            tolerance: 0.5,
+        }
+    }
+}
+
+/// A layout mistake found in a user interface
+///
+/// Widgets are identified by their path in the [`Dump`], as in
+/// [`dump::diff`](crate::dump::diff).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// Two sibling widgets overlap
+    Overlap {
+        /// The path of the first widget
+        first: String,
+        /// The path of the second widget
+        second: String,
+        /// The overlapping region
+        region: Rectangle,
+    },
+    /// A widget extends outside its parent
+    OutsideParent {
+        /// The path of the widget
+        path: String,
+        /// The bounds of the widget
+        bounds: Rectangle,
+        /// The bounds of its parent
+        parent: Rectangle,
+    },
+    /// A widget extends outside the viewport
+    OutsideViewport {
+        /// The path of the widget
+        path: String,
+        /// The bounds of the widget
+        bounds: Rectangle,
+    },
+    /// Some text needs more space than it was given
+    ClippedText {
+        /// The text content
+        text: String,
+        /// The bounds of the text
+        bounds: Rectangle,
+        /// The size the text needs
+        required: Size,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Overlap {
+                first,
+                second,
+                region,
+            } => write!(
+                f,
+                "{first} overlaps {second} by {}x{}",
+                region.width, region.height
+            ),
+            Issue::OutsideParent {
+                path,
+                bounds,
+                parent,
+            } => write!(
+                f,
+                "{path} at {bounds:?} is outside its parent at {parent:?}"
+            ),
+            Issue::OutsideViewport { path, bounds } => {
+                write!(f, "{path} at {bounds:?} is outside the viewport")
+            }
+            Issue::ClippedText {
+                text,
+                bounds,
+                required,
+            } => write!(
+                f,
+                "{text:?} needs {}x{} but only has {}x{}",
+                required.width, required.height, bounds.width, bounds.height
+            ),
+        }
+    }
+}
+
+/// Checks the widgets of a [`Dump`] for overlaps and widgets out of bounds
+///
+/// Clipped text can only be detected while laying out; see
+/// [`Simulator::lint`](crate::simulator::Simulator::lint).
+pub fn check(dump: &Dump, checks: &Checks) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let viewport = Rectangle::with_size(Size::new(dump.width, dump.height));
+
+    check_children("", &dump.nodes, None, viewport, checks, &mut issues);
+
+    issues
+}
+
+fn check_children(
+    parent_path: &str,
+    nodes: &[Node],
+    parent: Option<Rectangle>,
+    viewport: Rectangle,
+    checks: &Checks,
+    issues: &mut Vec<Issue>,
+) {
+    let path = |index: usize| dump::path(parent_path, index);
+
+    for (index, node) in nodes.iter().enumerate() {
+        let bounds = Rectangle::from(node.bounds);
+
+        if checks.overlaps {
+            for (other_index, other) in nodes.iter().enumerate().skip(index + 1) {
+                let Some(region) = bounds.intersection(&Rectangle::from(other.bounds)) else {
+                    continue;
+                };
+
+                if region.width > checks.tolerance && region.height > checks.tolerance {
+                    issues.push(Issue::Overlap {
+                        first: path(index),
+                        second: path(other_index),
+                        region,
+                    });
+                }
+            }
+        }
+
+        let mut is_outside = false;
+
+        if checks.out_of_bounds {
+            if let Some(parent) =
+                parent.filter(|parent| !is_within(bounds, *parent, checks.tolerance))
+            {
+                is_outside = true;
+
+                issues.push(Issue::OutsideParent {
+                    path: path(index),
+                    bounds,
+                    parent,
+                });
+            }
+
+            if !is_within(bounds, viewport, checks.tolerance) {
+                is_outside = true;
+
+                issues.push(Issue::OutsideViewport {
+                    path: path(index),
+                    bounds,
+                });
+            }
+        }
+
+        // Only the outermost offending widget is reported, and the contents
+        // of a scrollable are expected to overflow
+        if is_outside || node.kind == Kind::Scrollable {
+            let checks = Checks {
+                out_of_bounds: false,
+                ..*checks
+            };
+
+            check_children(
+                &path(index),
+                &node.children,
+                None,
+                viewport,
+                &checks,
+                issues,
+            );
+        } else {
+            check_children(
+                &path(index),
+                &node.children,
+                Some(bounds),
+                viewport,
+                checks,
+                issues,
+            );
+        }
+    }
+}
+
+fn is_within(bounds: Rectangle, container: Rectangle, tolerance: f32) -> bool {
+    bounds.x >= container.x - tolerance
+        && bounds.y >= container.y - tolerance
+        && bounds.x + bounds.width <= container.x + container.width + tolerance
+        && bounds.y + bounds.height <= container.y + container.height + tolerance
+}
+
+// Flags every `text` widget whose paragraph is larger than its layout bounds
+pub(crate) fn check_text<P>(
+    tree: &widget::Tree,
+    layout: Layout<'_>,
+    tolerance: f32,
+    issues: &mut Vec<Issue>,
+) where
+    P: Paragraph + 'static,
+{
+    if tree.tag == tree::Tag::of::<widget::text::State<P>>() {
+        let paragraph = tree.state.downcast_ref::<widget::text::State<P>>();
+        let bounds = layout.bounds();
+        let required = paragraph.min_bounds();
+
+        if required.width > bounds.width + tolerance || required.height > bounds.height + tolerance
+        {
+            issues.push(Issue::ClippedText {
+                text: paragraph.content().to_owned(),
+                bounds,
+                required,
+            });
+        }
+    }
+
+    for (child, layout) in tree.children.iter().zip(layout.children()) {
+        check_text::<P>(child, layout, tolerance, issues);
+    }
+}
@@ -0,0 +1,427 @@
+//! Render a view across a matrix of device viewports and scale factors
+//!
+//! Responsive breakpoints are found by dumping the layout tree at each device
+//! size and comparing the dumps of adjacent devices: a breakpoint lies
+//! between two devices whose trees differ in structure, kind or text, or
+//! whose widgets are arranged differently, like a row becoming a column. A
+//! widget that is merely resized or moved does not make a breakpoint.
+use iced::advanced::renderer;
+use iced::advanced::renderer::Headless;
+use iced::{Element, Size, Theme, window};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+use crate::dump::{self, Change, Dump, Node};
+use crate::export;
+use crate::simulator::Simulator;
+
+/// A named viewport size, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Device {
+    /// The name of the device
+    pub name: &'static str,
+    /// The logical size of its viewport
+    pub size: Size,
+}
+
+impl Device {
+    /// A phone in portrait orientation
+    pub const PHONE: Device = Device::new("phone", 390.0, 844.0);
+
+    /// A tablet in portrait orientation
+    pub const TABLET: Device = Device::new("tablet", 820.0, 1180.0);
+
+    /// A desktop window
+    pub const DESKTOP: Device = Device::new("desktop", 1440.0, 900.0);
+
+    /// The built-in device presets, from the narrowest to the widest
+    pub const PRESETS: [Device; 3] = [Device::PHONE, Device::TABLET, Device::DESKTOP];
+
+    /// Creates a [`Device`] with the given name and logical size
+    pub const fn new(name: &'static str, width: f32, height: f32) -> Self {
+        Self {
+            name,
+            size: Size::new(width, height),
+        }
+    }
+}
+
+/// The scale factors of the built-in presets
+pub const SCALE_FACTORS: [f32; 4] = [1.0, 1.5, 2.0, 3.0];
+
+/// A combination of [`Device`]s and scale factors to render
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Matrix {
+    devices: Vec<Device>,
+    scale_factors: Vec<f32>,
+}
+
+impl Matrix {
+    /// Creates an empty [`Matrix`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a [`Matrix`] of every [`Device::PRESETS`] at every one of the
+    /// [`SCALE_FACTORS`]
+    pub fn presets() -> Self {
+        Self::new()
+            .devices(Device::PRESETS)
+            .scale_factors(SCALE_FACTORS)
+    }
+
+    /// Adds a [`Device`] to the [`Matrix`]
+    pub fn device(mut self, device: Device) -> Self {
+        self.devices.push(device);
+        self
+    }
+
+    /// Adds the [`Device`]s to the [`Matrix`]
+    pub fn devices(mut self, devices: impl IntoIterator<Item = Device>) -> Self {
+        self.devices.extend(devices);
+        self
+    }
+
+    /// Sets the scale factors each [`Device`] is rendered at
+    ///
+    /// Defaults to `1.0` if none is given.
+    pub fn scale_factors(mut self, scale_factors: impl IntoIterator<Item = f32>) -> Self {
+        self.scale_factors = scale_factors.into_iter().collect();
+        self
+    }
+
+    /// Takes a labeled screenshot of the view for every device and scale
+    /// factor of the [`Matrix`]
+    pub fn render<'a, R, Message, E>(
+        &self,
+        simulator: &mut Simulator<R>,
+        view: impl Fn() -> E,
+    ) -> Result<Vec<Shot>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        self.combinations()
+            .map(|(device, scale_factor)| {
+                Ok(Shot {
+                    device,
+                    scale_factor,
+                    screenshot: simulator.screenshot(view(), device.size, scale_factor)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Renders the [`Matrix`] and writes each screenshot as a PNG named after
+    /// its label in the given directory, returning the written paths
+    pub fn render_to<'a, R, Message, E>(
+        &self,
+        simulator: &mut Simulator<R>,
+        view: impl Fn() -> E,
+        directory: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        let directory = directory.as_ref();
+
+        self.render(simulator, view)?
+            .into_iter()
+            .map(|shot| {
+                let path = directory.join(format!("{}.png", shot.label()));
+                let metadata = simulator.metadata(&shot.screenshot);
+
+                export::save(&shot.screenshot, Some(&metadata), &path)?;
+
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Finds the responsive breakpoints of the view between the devices of
+    /// the [`Matrix`], sorted by width
+    pub fn breakpoints<'a, R, Message, E>(
+        &self,
+        simulator: &mut Simulator<R>,
+        view: impl Fn() -> E,
+    ) -> Vec<Breakpoint>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        let mut devices = self.devices.clone();
+        devices.sort_by(|a, b| a.size.width.total_cmp(&b.size.width));
+
+        let dumps: Vec<_> = devices
+            .iter()
+            .map(|device| simulator.dump(view(), device.size))
+            .collect();
+
+        devices
+            .windows(2)
+            .zip(dumps.windows(2))
+            .filter_map(|(devices, dumps)| {
+                let changes = responsive_changes(&dumps[0], &dumps[1]);
+
+                (!changes.is_empty()).then(|| Breakpoint {
+                    from: devices[0],
+                    to: devices[1],
+                    changes,
+                })
+            })
+            .collect()
+    }
+
+    // Returns every device at every scale factor, in rendering order
+    fn combinations(&self) -> impl Iterator<Item = (Device, f32)> + '_ {
+        self.devices.iter().flat_map(|&device| {
+            self.resolved_scale_factors()
+                .iter()
+                .map(move |&scale_factor| (device, scale_factor))
+        })
+    }
+
+    fn resolved_scale_factors(&self) -> &[f32] {
+        if self.scale_factors.is_empty() {
+            &[1.0]
+        } else {
+            &self.scale_factors
+        }
+    }
+}
+
+// Returns the changes between two dumps that are not explained by the size of
+// the viewport alone: structural changes, and the bounds of the widgets that
+// moved to another side of their previous sibling
+fn responsive_changes(old: &Dump, new: &Dump) -> Vec<Change> {
+    let mut rearranged = BTreeSet::new();
+    find_rearranged("", &old.nodes, &new.nodes, &mut rearranged);
+
+    dump::diff(old, new, 0.0)
+        .into_iter()
+        .filter(|change| match change {
+            Change::Viewport { .. } => false,
+            Change::Bounds { path, .. } => rearranged.contains(path),
+            _ => true,
+        })
+        .collect()
+}
+
+fn find_rearranged(parent: &str, old: &[Node], new: &[Node], rearranged: &mut BTreeSet<String>) {
+    for (index, (old_node, new_node)) in old.iter().zip(new).enumerate() {
+        let path = dump::path(parent, index);
+
+        if index > 0 {
+            let before = Placement::of(&old[index - 1], old_node);
+            let after = Placement::of(&new[index - 1], new_node);
+
+            if before != after {
+                let _ = rearranged.insert(path.clone());
+            }
+        }
+
+        find_rearranged(&path, &old_node.children, &new_node.children, rearranged);
+    }
+}
+
+// Where a widget lies relative to its previous sibling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Right,
+    Left,
+    Below,
+    Above,
+    Overlapping,
+}
+
+impl Placement {
+    fn of(previous: &Node, node: &Node) -> Self {
+        // Tolerates the rounding of layouts
+        const EPSILON: f32 = 0.5;
+
+        let (a, b) = (previous.bounds, node.bounds);
+
+        if b.x >= a.x + a.width - EPSILON {
+            Placement::Right
+        } else if b.x + b.width <= a.x + EPSILON {
+            Placement::Left
+        } else if b.y >= a.y + a.height - EPSILON {
+            Placement::Below
+        } else if b.y + b.height <= a.y + EPSILON {
+            Placement::Above
+        } else {
+            Placement::Overlapping
+        }
+    }
+}
+
+/// A screenshot of a [`Matrix`]
+#[derive(Debug, Clone)]
+pub struct Shot {
+    /// The [`Device`] of the screenshot
+    pub device: Device,
+    /// The scale factor of the screenshot
+    pub scale_factor: f32,
+    /// The screenshot
+    pub screenshot: window::Screenshot,
+}
+
+impl Shot {
+    /// Returns the label of the [`Shot`], like `phone@1.5x`
+    pub fn label(&self) -> String {
+        format!("{}@{}x", self.device.name, self.scale_factor)
+    }
+}
+
+/// A change in the widget tree between two adjacent devices
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    /// The narrower device
+    pub from: Device,
+    /// The wider device
+    pub to: Device,
+    /// The structural changes of the widget tree, including the bounds of
+    /// the widgets that were rearranged
+    pub changes: Vec<Change>,
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "between {} ({}px) and {} ({}px): {} change(s)",
+            self.from.name,
+            self.from.size.width,
+            self.to.name,
+            self.to.size.width,
+            self.changes.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::Bounds;
+    use crate::simulator::Kind;
+
+    fn node(kind: Kind, x: f32, y: f32, width: f32, height: f32) -> Node {
+        Node {
+            kind,
+            id: None,
+            bounds: Bounds {
+                x,
+                y,
+                width,
+                height,
+            },
+            text: None,
+            children: Vec::new(),
+        }
+    }
+
+    fn dump(width: f32, children: Vec<Node>) -> Dump {
+        Dump {
+            width,
+            height: 800.0,
+            nodes: vec![Node {
+                children,
+                ..node(Kind::Container, 0.0, 0.0, width, 800.0)
+            }],
+        }
+    }
+
+    #[test]
+    fn resizing_is_not_a_breakpoint() {
+        let narrow = dump(
+            400.0,
+            vec![
+                node(Kind::Text, 0.0, 0.0, 200.0, 20.0),
+                node(Kind::Text, 200.0, 0.0, 200.0, 20.0),
+            ],
+        );
+
+        let wide = dump(
+            800.0,
+            vec![
+                node(Kind::Text, 0.0, 0.0, 400.0, 20.0),
+                node(Kind::Text, 400.0, 0.0, 400.0, 20.0),
+            ],
+        );
+
+        assert_eq!(responsive_changes(&narrow, &wide), Vec::new());
+    }
+
+    #[test]
+    fn a_column_becoming_a_row_is_a_breakpoint() {
+        let narrow = dump(
+            400.0,
+            vec![
+                node(Kind::Text, 0.0, 0.0, 400.0, 20.0),
+                node(Kind::Text, 0.0, 20.0, 400.0, 20.0),
+            ],
+        );
+
+        let wide = dump(
+            800.0,
+            vec![
+                node(Kind::Text, 0.0, 0.0, 400.0, 20.0),
+                node(Kind::Text, 400.0, 0.0, 400.0, 20.0),
+            ],
+        );
+
+        let paths: Vec<_> = responsive_changes(&narrow, &wide)
+            .into_iter()
+            .map(|change| match change {
+                Change::Bounds { path, .. } => path,
+                change => panic!("unexpected change: {change}"),
+            })
+            .collect();
+
+        assert_eq!(paths, ["0/1"]);
+    }
+
+    #[test]
+    fn reordering_is_a_breakpoint() {
+        let before = dump(
+            800.0,
+            vec![
+                node(Kind::Text, 0.0, 0.0, 400.0, 20.0),
+                node(Kind::Text, 400.0, 0.0, 400.0, 20.0),
+            ],
+        );
+
+        let after = dump(
+            1000.0,
+            vec![
+                node(Kind::Text, 500.0, 0.0, 500.0, 20.0),
+                node(Kind::Text, 0.0, 0.0, 500.0, 20.0),
+            ],
+        );
+
+        assert_eq!(responsive_changes(&before, &after).len(), 1);
+    }
+
+    #[test]
+    fn presets_render_every_device_at_every_scale_factor() {
+        let combinations: Vec<_> = Matrix::presets().combinations().collect();
+
+        assert_eq!(combinations.len(), 3 * 4);
+        assert_eq!(combinations[0], (Device::PHONE, 1.0));
+        assert_eq!(combinations[4], (Device::TABLET, 1.0));
+        assert_eq!(combinations[11], (Device::DESKTOP, 3.0));
+    }
+
+    #[test]
+    fn scale_factors_default_to_one() {
+        let combinations: Vec<_> = Matrix::new().device(Device::PHONE).combinations().collect();
+
+        assert_eq!(combinations, [(Device::PHONE, 1.0)]);
+    }
+}
@@ -0,0 +1,432 @@
+//! Perceptual image comparison metrics
+//!
+//! Every metric compares a reference screenshot against a candidate of the
+//! same size. Higher PSNR and SSIM values mean the candidate is closer to the
+//! reference, so they can be used both as snapshot tolerances and to rank
+//! different renderings of the same content.
+//!
+//! The metrics only look at color: alpha is ignored, so screenshots that
+//! only differ in transparency are identical to them.
+use iced::window;
+
+use crate::Error;
+use crate::diff;
+
+// The weights of each scale of MS-SSIM, from finest to coarsest
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+// The size and stride of the windows used by SSIM
+const WINDOW: usize = 8;
+const STRIDE: usize = 4;
+
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+// The color difference mapped to white in a difference map
+const MAX_DELTA_E: f64 = 20.0;
+
+/// The metrics of a candidate screenshot against a reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// The peak signal-to-noise ratio, in decibels
+    pub psnr: f64,
+    /// The structural similarity index, from -1 to 1
+    pub ssim: f64,
+    /// The multi-scale structural similarity index, from 0 to 1
+    pub ms_ssim: f64,
+    /// The mean perceptual color difference (CIE ΔE*ab)
+    pub mean_delta_e: f64,
+}
+
+impl Metrics {
+    /// Computes all the [`Metrics`] of the candidate against the reference
+    pub fn compute(
+        reference: &window::Screenshot,
+        candidate: &window::Screenshot,
+    ) -> Result<Self, Error> {
+        Ok(Metrics {
+            psnr: psnr(reference, candidate)?,
+            ssim: ssim(reference, candidate)?,
+            ms_ssim: ms_ssim(reference, candidate)?,
+            mean_delta_e: color_difference(reference, candidate)?.mean,
+        })
+    }
+}
+
+/// A per-pixel perceptual color difference between two screenshots
+#[derive(Debug, Clone)]
+pub struct ColorDifference {
+    /// The mean color difference (CIE ΔE*ab)
+    pub mean: f64,
+    /// The largest color difference (CIE ΔE*ab)
+    pub max: f64,
+    /// A heatmap of the color differences, saturating at a ΔE*ab of 20
+    pub map: window::Screenshot,
+}
+
+/// Computes the peak signal-to-noise ratio of the RGB channels, in decibels
+///
+/// Identical screenshots have an infinite PSNR.
+pub fn psnr(reference: &window::Screenshot, candidate: &window::Screenshot) -> Result<f64, Error> {
+    check_sizes(reference, candidate)?;
+
+    let (sum, count) = reference
+        .bytes
+        .chunks_exact(4)
+        .zip(candidate.bytes.chunks_exact(4))
+        .flat_map(|(r, c)| r[..3].iter().zip(&c[..3]))
+        .fold((0.0, 0_usize), |(sum, count), (r, c)| {
+            let error = f64::from(*r) - f64::from(*c);
+
+            (sum + error * error, count + 1)
+        });
+
+    if count == 0 || sum == 0.0 {
+        return Ok(f64::INFINITY);
+    }
+
+    let mse = sum / count as f64;
+
+    Ok(10.0 * (255.0 * 255.0 / mse).log10())
+}
+
+/// Computes the structural similarity index of the luma of both screenshots
+///
+/// Alpha is ignored.
+pub fn ssim(reference: &window::Screenshot, candidate: &window::Screenshot) -> Result<f64, Error> {
+    check_sizes(reference, candidate)?;
+
+    let reference = Plane::luma(reference);
+    let candidate = Plane::luma(candidate);
+
+    Ok(compare_windows(&reference, &candidate).0)
+}
+
+/// Computes the multi-scale structural similarity index of the luma of both
+/// screenshots
+///
+/// Fewer scales are used when the screenshots are too small to be halved
+/// five times.
+pub fn ms_ssim(
+    reference: &window::Screenshot,
+    candidate: &window::Screenshot,
+) -> Result<f64, Error> {
+    check_sizes(reference, candidate)?;
+
+    let mut reference = Plane::luma(reference);
+    let mut candidate = Plane::luma(candidate);
+
+    let mut scales = Vec::with_capacity(MS_SSIM_WEIGHTS.len());
+
+    for scale in 0..MS_SSIM_WEIGHTS.len() {
+        let (ssim, contrast_structure) = compare_windows(&reference, &candidate);
+
+        let is_last = scale + 1 == MS_SSIM_WEIGHTS.len()
+            || reference.width / 2 < WINDOW
+            || reference.height / 2 < WINDOW;
+
+        if is_last {
+            scales.push(ssim);
+            break;
+        }
+
+        scales.push(contrast_structure);
+
+        reference = reference.halve();
+        candidate = candidate.halve();
+    }
+
+    let weights = &MS_SSIM_WEIGHTS[..scales.len()];
+    let total: f64 = weights.iter().sum();
+
+    Ok(scales
+        .iter()
+        .zip(weights)
+        .map(|(value, weight)| value.max(0.0).powf(weight / total))
+        .product())
+}
+
+/// Computes the perceptual color difference (CIE ΔE*ab) of every pixel
+pub fn color_difference(
+    reference: &window::Screenshot,
+    candidate: &window::Screenshot,
+) -> Result<ColorDifference, Error> {
+    check_sizes(reference, candidate)?;
+
+    let mut total = 0.0;
+    let mut max = 0.0_f64;
+    let mut map = Vec::with_capacity(reference.bytes.len());
+
+    for (r, c) in reference
+        .bytes
+        .chunks_exact(4)
+        .zip(candidate.bytes.chunks_exact(4))
+    {
+        let [l1, a1, b1] = lab(r);
+        let [l2, a2, b2] = lab(c);
+
+        let delta_e = ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt();
+
+        total += delta_e;
+        max = max.max(delta_e);
+
+        let error = (delta_e / MAX_DELTA_E).min(1.0) * 255.0;
+
+        map.extend_from_slice(&diff::heat(error.round() as u8));
+    }
+
+    let pixels = reference.bytes.len() / 4;
+
+    Ok(ColorDifference {
+        mean: if pixels == 0 {
+            0.0
+        } else {
+            total / pixels as f64
+        },
+        max,
+        map: window::Screenshot::new(map, reference.size, reference.scale_factor),
+    })
+}
+
+fn check_sizes(
+    reference: &window::Screenshot,
+    candidate: &window::Screenshot,
+) -> Result<(), Error> {
+    if reference.size != candidate.size {
+        return Err(Error::SizeMismatch {
+            expected: reference.size,
+            found: candidate.size,
+        });
+    }
+
+    Ok(())
+}
+
+// Returns the mean SSIM and the mean contrast-structure term of all windows
+fn compare_windows(reference: &Plane, candidate: &Plane) -> (f64, f64) {
+    let window = WINDOW.min(reference.width).min(reference.height);
+
+    if window == 0 {
+        return (1.0, 1.0);
+    }
+
+    let mut ssim = 0.0;
+    let mut contrast_structure = 0.0;
+    let mut count = 0;
+
+    let mut y = 0;
+
+    while y + window <= reference.height {
+        let mut x = 0;
+
+        while x + window <= reference.width {
+            let (mean_r, mean_c, variance_r, variance_c, covariance) =
+                statistics(reference, candidate, x, y, window);
+
+            let luminance = (2.0 * mean_r * mean_c + C1) / (mean_r * mean_r + mean_c * mean_c + C1);
+            let cs = (2.0 * covariance + C2) / (variance_r + variance_c + C2);
+
+            ssim += luminance * cs;
+            contrast_structure += cs;
+            count += 1;
+
+            x += STRIDE;
+        }
+
+        y += STRIDE;
+    }
+
+    (ssim / count as f64, contrast_structure / count as f64)
+}
+
+fn statistics(
+    reference: &Plane,
+    candidate: &Plane,
+    x: usize,
+    y: usize,
+    window: usize,
+) -> (f64, f64, f64, f64, f64) {
+    let n = (window * window) as f64;
+
+    let pixels = || {
+        (y..y + window).flat_map(move |row| {
+            (x..x + window).map(move |column| {
+                let index = row * reference.width + column;
+
+                (reference.data[index], candidate.data[index])
+            })
+        })
+    };
+
+    let (sum_r, sum_c) = pixels().fold((0.0, 0.0), |(r, c), (pr, pc)| (r + pr, c + pc));
+    let (mean_r, mean_c) = (sum_r / n, sum_c / n);
+
+    let (variance_r, variance_c, covariance) =
+        pixels().fold((0.0, 0.0, 0.0), |(vr, vc, cov), (pr, pc)| {
+            let (dr, dc) = (pr - mean_r, pc - mean_c);
+
+            (vr + dr * dr, vc + dc * dc, cov + dr * dc)
+        });
+
+    // Unbiased estimators, as in the reference implementation
+    let m = (n - 1.0).max(1.0);
+
+    (
+        mean_r,
+        mean_c,
+        variance_r / m,
+        variance_c / m,
+        covariance / m,
+    )
+}
+
+// A single channel image
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+}
+
+impl Plane {
+    fn luma(screenshot: &window::Screenshot) -> Self {
+        Plane {
+            width: screenshot.size.width as usize,
+            height: screenshot.size.height as usize,
+            data: screenshot
+                .bytes
+                .chunks_exact(4)
+                .map(|p| {
+                    0.299 * f64::from(p[0]) + 0.587 * f64::from(p[1]) + 0.114 * f64::from(p[2])
+                })
+                .collect(),
+        }
+    }
+
+    // Downsamples the plane by averaging 2x2 blocks
+    fn halve(&self) -> Self {
+        let width = self.width / 2;
+        let height = self.height / 2;
+
+        let data = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| {
+                    let at = |dx, dy| self.data[(2 * y + dy) * self.width + 2 * x + dx];
+
+                    (at(0, 0) + at(1, 0) + at(0, 1) + at(1, 1)) / 4.0
+                })
+            })
+            .collect();
+
+        Plane {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+// Converts an sRGB pixel into CIELAB under a D65 white point
+fn lab(pixel: &[u8]) -> [f64; 3] {
+    let linear = |channel: u8| {
+        let c = f64::from(channel) / 255.0;
+
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    let (r, g, b) = (linear(pixel[0]), linear(pixel[1]), linear(pixel[2]));
+
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::Size;
+
+    fn screenshot(width: u32, height: u32, pixel: impl Fn(usize) -> [u8; 4]) -> window::Screenshot {
+        let bytes: Vec<u8> = (0..(width * height) as usize).flat_map(pixel).collect();
+
+        window::Screenshot::new(bytes, Size::new(width, height), 1.0)
+    }
+
+    fn gray(value: u8) -> impl Fn(usize) -> [u8; 4] {
+        move |_| [value, value, value, 255]
+    }
+
+    #[test]
+    fn identical_screenshots_are_perfect() {
+        let reference = screenshot(32, 32, |i| [(i % 256) as u8, 50, 200, 255]);
+
+        let metrics = Metrics::compute(&reference, &reference).unwrap();
+
+        assert_eq!(metrics.psnr, f64::INFINITY);
+        assert!((metrics.ssim - 1.0).abs() < 1e-12);
+        assert!((metrics.ms_ssim - 1.0).abs() < 1e-12);
+        assert_eq!(metrics.mean_delta_e, 0.0);
+    }
+
+    #[test]
+    fn known_noise_has_a_known_psnr() {
+        let reference = screenshot(16, 16, gray(100));
+        let candidate = screenshot(16, 16, |i| {
+            let value = if i % 2 == 0 { 110 } else { 90 };
+
+            [value, value, value, 255]
+        });
+
+        // Every channel is off by 10, so the mean squared error is 100
+        let expected = 10.0 * (255.0_f64 * 255.0 / 100.0).log10();
+
+        assert!((psnr(&reference, &candidate).unwrap() - expected).abs() < 1e-9);
+        assert!((expected - 28.1308).abs() < 1e-4);
+    }
+
+    #[test]
+    fn size_mismatches_are_errors() {
+        let reference = screenshot(8, 8, gray(0));
+        let candidate = screenshot(8, 9, gray(0));
+
+        assert!(matches!(
+            Metrics::compute(&reference, &candidate),
+            Err(Error::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn screenshots_smaller_than_a_window_are_compared() {
+        let reference = screenshot(3, 5, gray(0));
+        let candidate = screenshot(3, 5, gray(255));
+
+        assert!((ssim(&reference, &reference).unwrap() - 1.0).abs() < 1e-12);
+        assert!((ms_ssim(&reference, &reference).unwrap() - 1.0).abs() < 1e-12);
+        assert!(ssim(&reference, &candidate).unwrap() < 0.1);
+        assert!(ms_ssim(&reference, &candidate).unwrap().is_finite());
+    }
+
+    #[test]
+    fn alpha_is_ignored() {
+        let reference = screenshot(16, 16, |_| [10, 20, 30, 255]);
+        let candidate = screenshot(16, 16, |_| [10, 20, 30, 0]);
+
+        assert_eq!(psnr(&reference, &candidate).unwrap(), f64::INFINITY);
+        assert!((ssim(&reference, &candidate).unwrap() - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,303 @@
+//! Render many screenshots in parallel on a pool of simulators
+//!
+//! Each worker thread owns its own [`Simulator`], so elements are built on
+//! the thread that renders them and only the view factories need to be
+//! [`Send`]. The futures returned by a [`Pool`] never block the executor that
+//! polls them, so they compose with async runtimes and `sipper` streams.
+use iced::advanced::renderer;
+use iced::advanced::renderer::Headless;
+use iced::futures::channel::mpsc::unbounded;
+use iced::futures::channel::oneshot;
+use iced::futures::executor;
+use iced::futures::stream::{FuturesUnordered, StreamExt};
+use iced::{Element, Size, Theme, window};
+use sipper::{Sipper, sipper};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use crate::Error;
+use crate::simulator::{Background, Builder, Simulator};
+
+/// A screenshot to be taken by a [`Pool`]
+pub struct Job<R = iced::Renderer> {
+    view: Box<dyn FnOnce() -> Element<'static, (), Theme, R> + Send>,
+    size: Size,
+    scale_factor: f32,
+    theme: Option<Theme>,
+    background: Option<Background>,
+}
+
+impl<R> Job<R>
+where
+    R: renderer::Renderer + 'static,
+{
+    /// Creates a [`Job`] that takes a screenshot of the element built by the
+    /// given factory
+    pub fn new<Message>(
+        view: impl FnOnce() -> Element<'static, Message, Theme, R> + Send + 'static,
+        size: impl Into<Size>,
+        scale_factor: f32,
+    ) -> Self
+    where
+        Message: 'static,
+    {
+        Self {
+            view: Box::new(move || view().map(|_| ())),
+            size: size.into(),
+            scale_factor,
+            theme: None,
+            background: None,
+        }
+    }
+
+    /// Sets the [`Theme`] of the screenshot, instead of the one of the simulator
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Sets the [`Background`] of the screenshot, instead of the one of the
+    /// simulator
+    pub fn background(mut self, background: impl Into<Background>) -> Self {
+        self.background = Some(background.into());
+        self
+    }
+}
+
+/// The progress of a batch of [`Job`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The amount of finished jobs
+    pub finished: usize,
+    /// The total amount of jobs
+    pub total: usize,
+}
+
+type Request<R> = (Job<R>, oneshot::Sender<Result<window::Screenshot, Error>>);
+
+/// A pool of [`Simulator`]s rendering [`Job`]s on worker threads
+///
+/// Dropping the [`Pool`] does not block: its workers finish the queued jobs
+/// and exit in the background. Use [`Pool::shutdown`] to wait for them.
+pub struct Pool<R = iced::Renderer> {
+    sender: Option<mpsc::Sender<Request<R>>>,
+    workers: Vec<thread::JoinHandle<()>>,
+    renderer: PhantomData<fn() -> R>,
+}
+
+impl<R> Pool<R>
+where
+    R: renderer::Renderer + Headless + Send + 'static,
+{
+    /// Creates a [`Pool`] of simulators with the default settings
+    pub fn new(workers: usize) -> Result<Self, Error> {
+        Self::with_builder(workers, Builder::default())
+    }
+
+    /// Creates a [`Pool`] of simulators built by the given [`Builder`]
+    ///
+    /// This blocks the current thread until every simulator is ready; use
+    /// [`Pool::spawn`] inside of an async runtime.
+    ///
+    /// Fails if any of the simulators cannot be created.
+    pub fn with_builder(workers: usize, builder: Builder) -> Result<Self, Error> {
+        executor::block_on(Self::spawn(workers, builder))
+    }
+
+    /// Creates a [`Pool`] of simulators built by the given [`Builder`],
+    /// resolving once every simulator is ready
+    ///
+    /// Fails if any of the simulators cannot be created.
+    pub async fn spawn(workers: usize, builder: Builder) -> Result<Self, Error> {
+        let (sender, receiver) = mpsc::channel::<Request<R>>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (ready_sender, mut ready) = unbounded();
+
+        let workers: Vec<_> = (0..workers.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let builder = builder.clone();
+                let ready = ready_sender.clone();
+
+                thread::spawn(move || {
+                    let mut simulator = match builder.build::<R>() {
+                        Ok(simulator) => simulator,
+                        Err(error) => {
+                            let _ = ready.unbounded_send(Err(error));
+                            return;
+                        }
+                    };
+
+                    // The pool is ready once every worker has dropped its sender
+                    let _ = ready.unbounded_send(Ok(()));
+                    drop(ready);
+
+                    work(&mut simulator, &receiver);
+                })
+            })
+            .collect();
+
+        drop(ready_sender);
+
+        // Dropping the pool on failure stops the workers that did start,
+        // without waiting for them
+        let pool = Pool {
+            sender: Some(sender),
+            workers,
+            renderer: PhantomData,
+        };
+
+        while let Some(result) = ready.next().await {
+            result?;
+        }
+
+        Ok(pool)
+    }
+
+    /// Stops the [`Pool`], resolving once its workers have finished the
+    /// queued jobs and exited
+    ///
+    /// The workers are joined on a thread of their own, so this does not
+    /// block the current executor.
+    pub async fn shutdown(mut self) {
+        drop(self.sender.take());
+
+        let workers = std::mem::take(&mut self.workers);
+        let (sender, receiver) = oneshot::channel();
+
+        let _ = thread::spawn(move || {
+            for worker in workers {
+                let _ = worker.join();
+            }
+
+            let _ = sender.send(());
+        });
+
+        let _ = receiver.await;
+    }
+
+    /// Returns the amount of worker threads of the [`Pool`]
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Takes a screenshot of the element built by the given factory on a
+    /// worker, without blocking the current executor
+    ///
+    /// This is a shorthand for [`Pool::render`] with a new [`Job`].
+    pub fn screenshot<Message>(
+        &self,
+        view: impl FnOnce() -> Element<'static, Message, Theme, R> + Send + 'static,
+        size: impl Into<Size>,
+        scale_factor: f32,
+    ) -> impl Future<Output = Result<window::Screenshot, Error>> + Send + 'static
+    where
+        Message: 'static,
+    {
+        self.render(Job::new(view, size, scale_factor))
+    }
+
+    /// Queues a [`Job`], returning a future that resolves to its screenshot
+    pub fn render(
+        &self,
+        job: Job<R>,
+    ) -> impl Future<Output = Result<window::Screenshot, Error>> + Send + 'static {
+        let (sender, receiver) = oneshot::channel();
+
+        let is_queued = self
+            .sender
+            .as_ref()
+            .is_some_and(|queue| queue.send((job, sender)).is_ok());
+
+        async move {
+            if !is_queued {
+                return Err(Error::WorkerStopped);
+            }
+
+            receiver.await.unwrap_or(Err(Error::WorkerStopped))
+        }
+    }
+
+    /// Queues all the [`Job`]s, reporting [`Progress`] as they finish
+    ///
+    /// The screenshots are returned in the order of the jobs.
+    pub fn render_all(
+        &self,
+        jobs: impl IntoIterator<Item = Job<R>>,
+    ) -> impl Sipper<Vec<Result<window::Screenshot, Error>>, Progress> + Send + 'static {
+        let mut pending: FuturesUnordered<_> = jobs
+            .into_iter()
+            .enumerate()
+            .map(|(index, job)| {
+                let screenshot = self.render(job);
+
+                async move { (index, screenshot.await) }
+            })
+            .collect();
+
+        let total = pending.len();
+
+        sipper(async move |mut progress| {
+            let mut results: Vec<_> = (0..total).map(|_| None).collect();
+            let mut finished = 0;
+
+            while let Some((index, result)) = pending.next().await {
+                results[index] = Some(result);
+                finished += 1;
+
+                progress.send(Progress { finished, total }).await;
+            }
+
+            results
+                .into_iter()
+                .map(|result| result.unwrap_or(Err(Error::WorkerStopped)))
+                .collect()
+        })
+    }
+}
+
+impl<R> Drop for Pool<R> {
+    // Closing the queue is enough for the workers to exit once they are idle;
+    // their handles are dropped, detaching them
+    fn drop(&mut self) {
+        drop(self.sender.take());
+    }
+}
+
+// Renders jobs until the pool is dropped
+fn work<R>(simulator: &mut Simulator<R>, receiver: &Mutex<mpsc::Receiver<Request<R>>>)
+where
+    R: renderer::Renderer + Headless + Send + 'static,
+{
+    loop {
+        let request = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        let Ok((job, sender)) = request else {
+            return;
+        };
+
+        let background = simulator.background();
+
+        if let Some(custom) = job.background {
+            simulator.set_background(custom);
+        }
+
+        let render = |simulator: &mut Simulator<R>| {
+            simulator.screenshot((job.view)(), job.size, job.scale_factor)
+        };
+
+        let result = match job.theme {
+            Some(theme) => simulator.in_theme(theme, render),
+            None => render(simulator),
+        };
+
+        simulator.set_background(background);
+
+        let _ = sender.send(result);
+    }
+}
@@ -0,0 +1,152 @@
+//! Export sequences of screenshots taken at regular intervals
+//!
+//! A [`Recording`] can be saved as an animated PNG, which keeps the exact
+//! pixels of every frame, or as a YUV4MPEG2 video, which most video tools
+//! can read directly.
+use iced::window;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::Error;
+use crate::export;
+
+/// A sequence of screenshots of the same size, taken at a regular interval
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// The frames of the recording
+    pub frames: Vec<window::Screenshot>,
+    /// The time between two frames
+    pub interval: Duration,
+}
+
+impl Recording {
+    /// Encodes the [`Recording`] as an animated PNG that loops forever
+    pub fn encode_apng(&self) -> Result<Vec<u8>, Error> {
+        let first = self.first()?;
+        let mut apng = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut apng, first.size.width, first.size.height);
+            encoder.set_color(png::ColorType::Rgba);
+
+            encoder
+                .set_animated(self.frames.len() as u32, 0)
+                .map_err(|e| Error::Encode(e.to_string()))?;
+
+            let delay = u16::try_from(self.interval.as_millis()).unwrap_or(u16::MAX);
+
+            encoder
+                .set_frame_delay(delay, 1000)
+                .map_err(|e| Error::Encode(e.to_string()))?;
+
+            let mut writer = encoder
+                .write_header()
+                .map_err(|e| Error::Encode(e.to_string()))?;
+
+            for frame in &self.frames {
+                writer
+                    .write_image_data(&frame.bytes)
+                    .map_err(|e| Error::Encode(e.to_string()))?;
+            }
+
+            writer.finish().map_err(|e| Error::Encode(e.to_string()))?;
+        }
+
+        Ok(apng)
+    }
+
+    /// Encodes the [`Recording`] as an uncompressed YUV4MPEG2 video
+    ///
+    /// Frames are converted to 4:4:4 BT.601 and their alpha is discarded.
+    pub fn encode_y4m(&self) -> Result<Vec<u8>, Error> {
+        let first = self.first()?;
+        let micros = self.interval.as_micros().clamp(1, u128::from(u32::MAX));
+
+        let mut video = format!(
+            "YUV4MPEG2 W{} H{} F1000000:{micros} Ip A1:1 C444\n",
+            first.size.width, first.size.height
+        )
+        .into_bytes();
+
+        let pixels = first.bytes.len() / 4;
+
+        for frame in &self.frames {
+            video.extend_from_slice(b"FRAME\n");
+
+            let (mut y, mut u, mut v) = (
+                Vec::with_capacity(pixels),
+                Vec::with_capacity(pixels),
+                Vec::with_capacity(pixels),
+            );
+
+            for pixel in frame.bytes.chunks_exact(4) {
+                let [luma, blue, red] = yuv(pixel);
+
+                y.push(luma);
+                u.push(blue);
+                v.push(red);
+            }
+
+            video.extend(y);
+            video.extend(u);
+            video.extend(v);
+        }
+
+        Ok(video)
+    }
+
+    /// Writes the [`Recording`] to the given path, choosing the format from its extension
+    ///
+    /// `.png` and `.apng` produce an animated PNG, `.y4m` a YUV4MPEG2 video.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        let bytes = match extension.as_deref() {
+            Some("png" | "apng") => self.encode_apng()?,
+            Some("y4m") => self.encode_y4m()?,
+            _ => {
+                return Err(Error::Encode(format!(
+                    "unknown animation format for {path:?}"
+                )));
+            }
+        };
+
+        export::write_file(path, bytes)
+    }
+
+    // Returns the first frame, making sure every other frame has the same size
+    fn first(&self) -> Result<&window::Screenshot, Error> {
+        let first = self
+            .frames
+            .first()
+            .ok_or_else(|| Error::Encode("recording has no frames".to_owned()))?;
+
+        if let Some(frame) = self.frames.iter().find(|frame| frame.size != first.size) {
+            return Err(Error::SizeMismatch {
+                expected: first.size,
+                found: frame.size,
+            });
+        }
+
+        Ok(first)
+    }
+}
+
+// Converts an RGBA pixel into limited range BT.601 Y'CbCr
+fn yuv(pixel: &[u8]) -> [u8; 3] {
+    let (r, g, b) = (
+        f64::from(pixel[0]) / 255.0,
+        f64::from(pixel[1]) / 255.0,
+        f64::from(pixel[2]) / 255.0,
+    );
+
+    let y = 16.0 + 65.481 * r + 128.553 * g + 24.966 * b;
+    let u = 128.0 - 37.797 * r - 74.203 * g + 112.0 * b;
+    let v = 128.0 + 112.0 * r - 93.786 * g - 18.214 * b;
+
+    [y.round() as u8, u.round() as u8, v.round() as u8]
+}
@@ -0,0 +1,445 @@
+//! Compare directories of screenshots against directories of goldens
+//!
+//! Screenshots are paired by their path relative to the compared
+//! directories, so subdirectories, like the ones written per scenario by the
+//! CLI, are compared too. The `.actual.png` and `.diff.png` files written
+//! next to goldens by failed [`Snapshot`](crate::snapshot::Snapshot)
+//! comparisons are not screenshots themselves; use [`pending`] to review them.
+use iced::{Size, window};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+use crate::diff::{self, Diff};
+use crate::export;
+use crate::metrics::Metrics;
+use crate::snapshot::Tolerance;
+
+// The suffixes of the files written next to goldens by failed snapshot comparisons
+const ACTUAL_SUFFIX: &str = ".actual.png";
+const DIFF_SUFFIX: &str = ".diff.png";
+
+/// The result of comparing a screenshot against its golden
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    /// The screenshot matched the golden within tolerance
+    Passed,
+    /// The screenshot did not match the golden
+    Failed,
+    /// The screenshot and the golden have different dimensions
+    SizeMismatch {
+        /// The size of the golden
+        expected: Size<u32>,
+        /// The size of the screenshot
+        found: Size<u32>,
+    },
+    /// There is a golden but no screenshot
+    Missing,
+    /// There is a screenshot but no golden
+    New,
+}
+
+impl Status {
+    /// Returns whether the [`Status`] counts as a regression
+    ///
+    /// New screenshots are not regressions, but still need a golden.
+    pub fn is_regression(&self) -> bool {
+        matches!(
+            self,
+            Status::Failed | Status::SizeMismatch { .. } | Status::Missing
+        )
+    }
+}
+
+/// A screenshot compared against its golden
+#[derive(Debug, Clone)]
+pub struct Case {
+    /// The path shared by the golden and the screenshot, relative to their
+    /// directories and separated by `/`
+    pub name: String,
+    /// The path of the golden, if it exists
+    pub golden: Option<PathBuf>,
+    /// The path of the screenshot, if it exists
+    pub actual: Option<PathBuf>,
+    /// The result of the comparison
+    pub status: Status,
+    /// The per-pixel differences, if both images have the same size
+    pub diff: Option<Diff>,
+    /// The perceptual metrics, if both images have the same size
+    pub metrics: Option<Metrics>,
+}
+
+/// The amount of [`Case`]s with each [`Status`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// The amount of passed cases
+    pub passed: usize,
+    /// The amount of failed cases, including size mismatches
+    pub failed: usize,
+    /// The amount of missing screenshots
+    pub missing: usize,
+    /// The amount of new screenshots
+    pub new: usize,
+}
+
+impl Summary {
+    /// Counts the [`Case`]s with each [`Status`]
+    pub fn new<'a>(cases: impl IntoIterator<Item = &'a Case>) -> Self {
+        cases
+            .into_iter()
+            .fold(Summary::default(), |mut summary, case| {
+                match case.status {
+                    Status::Passed => summary.passed += 1,
+                    Status::Failed | Status::SizeMismatch { .. } => summary.failed += 1,
+                    Status::Missing => summary.missing += 1,
+                    Status::New => summary.new += 1,
+                }
+
+                summary
+            })
+    }
+
+    /// Returns whether any case is a regression
+    pub fn has_regressions(&self) -> bool {
+        self.failed > 0 || self.missing > 0
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} missing, {} new",
+            self.passed, self.failed, self.missing, self.new
+        )
+    }
+}
+
+/// Compares a screenshot against its golden
+pub fn compare(
+    name: impl Into<String>,
+    golden: &window::Screenshot,
+    actual: &window::Screenshot,
+    tolerance: &Tolerance,
+) -> Result<Case, Error> {
+    let mut case = Case {
+        name: name.into(),
+        golden: None,
+        actual: None,
+        status: Status::Passed,
+        diff: None,
+        metrics: None,
+    };
+
+    if golden.size != actual.size {
+        case.status = Status::SizeMismatch {
+            expected: golden.size,
+            found: actual.size,
+        };
+
+        return Ok(case);
+    }
+
+    let diff = diff::diff(golden, actual, tolerance.channel)?;
+
+    if !tolerance.accepts(diff.over_threshold, golden, actual)? {
+        case.status = Status::Failed;
+    }
+
+    case.metrics = Some(Metrics::compute(golden, actual)?);
+    case.diff = Some(diff);
+
+    Ok(case)
+}
+
+/// Compares every PNG screenshot in a directory and its subdirectories
+/// against the golden with the same relative path in another, sorted by name
+pub fn compare_directories(
+    goldens: impl AsRef<Path>,
+    actuals: impl AsRef<Path>,
+    tolerance: &Tolerance,
+) -> Result<Vec<Case>, Error> {
+    let goldens = goldens.as_ref();
+    let actuals = actuals.as_ref();
+
+    let golden_names = screenshots(goldens)?;
+    let actual_names = screenshots(actuals)?;
+
+    golden_names
+        .union(&actual_names)
+        .map(|name| {
+            let golden = golden_names.contains(name).then(|| goldens.join(name));
+            let actual = actual_names.contains(name).then(|| actuals.join(name));
+
+            let mut case = match (&golden, &actual) {
+                (Some(golden), Some(actual)) => {
+                    let (golden, _) = export::load(golden)?;
+                    let (actual, _) = export::load(actual)?;
+
+                    compare(name.clone(), &golden, &actual, tolerance)?
+                }
+                (golden, _) => Case {
+                    name: name.clone(),
+                    golden: None,
+                    actual: None,
+                    status: if golden.is_some() {
+                        Status::Missing
+                    } else {
+                        Status::New
+                    },
+                    diff: None,
+                    metrics: None,
+                },
+            };
+
+            case.golden = golden;
+            case.actual = actual;
+
+            Ok(case)
+        })
+        .collect()
+}
+
+/// Compares the `.actual.png` files left in a directory and its
+/// subdirectories by failed [`Snapshot`](crate::snapshot::Snapshot)
+/// comparisons against their goldens, sorted by name
+pub fn pending(directory: impl AsRef<Path>, tolerance: &Tolerance) -> Result<Vec<Case>, Error> {
+    let directory = directory.as_ref();
+
+    files(directory)?
+        .into_iter()
+        .filter_map(|name| {
+            name.strip_suffix(ACTUAL_SUFFIX)
+                .map(|stem| format!("{stem}.png"))
+        })
+        .map(|name| {
+            let golden = directory.join(&name);
+            let actual = directory.join(sibling(&name, ACTUAL_SUFFIX));
+
+            let mut case = if golden.exists() {
+                let (golden, _) = export::load(&golden)?;
+                let (actual, _) = export::load(&actual)?;
+
+                compare(name, &golden, &actual, tolerance)?
+            } else {
+                Case {
+                    name,
+                    golden: None,
+                    actual: None,
+                    status: Status::New,
+                    diff: None,
+                    metrics: None,
+                }
+            };
+
+            case.golden = golden.exists().then_some(golden);
+            case.actual = Some(actual);
+
+            Ok(case)
+        })
+        .collect()
+}
+
+/// Replaces the given golden with the actual screenshot of the [`Case`]
+///
+/// The `.actual.png` and `.diff.png` files of a failed
+/// [`Snapshot`](crate::snapshot::Snapshot) comparison are removed afterwards.
+pub fn approve(case: &Case, golden: impl AsRef<Path>) -> Result<(), Error> {
+    let golden = golden.as_ref();
+
+    let Some(actual) = &case.actual else {
+        return Ok(());
+    };
+
+    let bytes = fs::read(actual).map_err(|error| Error::Io {
+        path: actual.clone(),
+        error,
+    })?;
+
+    export::write_file(golden, bytes)?;
+
+    let is_pending = actual
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(ACTUAL_SUFFIX));
+
+    if is_pending {
+        let name = case.name.rsplit('/').next().unwrap_or(&case.name);
+
+        for path in [
+            actual.clone(),
+            actual.with_file_name(sibling(name, DIFF_SUFFIX)),
+        ] {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|error| Error::Io { path, error })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Lists the relative paths of the PNG screenshots in a directory
+fn screenshots(directory: &Path) -> Result<BTreeSet<String>, Error> {
+    Ok(files(directory)?
+        .into_iter()
+        .filter(|name| {
+            name.to_ascii_lowercase().ends_with(".png")
+                && !name.ends_with(ACTUAL_SUFFIX)
+                && !name.ends_with(DIFF_SUFFIX)
+        })
+        .collect())
+}
+
+// Lists the paths of the files in a directory and its subdirectories,
+// relative to it and separated by `/`
+fn files(directory: &Path) -> Result<BTreeSet<String>, Error> {
+    let mut names = BTreeSet::new();
+
+    collect_files(directory, "", &mut names)?;
+
+    Ok(names)
+}
+
+fn collect_files(
+    directory: &Path,
+    prefix: &str,
+    names: &mut BTreeSet<String>,
+) -> Result<(), Error> {
+    let entries = fs::read_dir(directory).map_err(|error| Error::Io {
+        path: directory.to_path_buf(),
+        error,
+    })?;
+
+    for entry in entries {
+        let entry = entry.map_err(|error| Error::Io {
+            path: directory.to_path_buf(),
+            error,
+        })?;
+
+        let path = entry.path();
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+
+        if path.is_dir() {
+            collect_files(&path, &format!("{name}/"), names)?;
+        } else if path.is_file() {
+            let _ = names.insert(name);
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the name of the file written next to a golden with the given suffix
+fn sibling(name: &str, suffix: &str) -> String {
+    let stem = name.strip_suffix(".png").unwrap_or(name);
+
+    format!("{stem}{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // A directory of its own for a test, removed when dropped
+    struct Directory(PathBuf);
+
+    impl Directory {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!(
+                "sleepy_hollow-regression-{name}-{}",
+                std::process::id()
+            ));
+
+            let _ = fs::remove_dir_all(&path);
+
+            Self(path)
+        }
+    }
+
+    impl Drop for Directory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn screenshot(value: u8) -> window::Screenshot {
+        window::Screenshot::new(vec![value; 8 * 8 * 4], Size::new(8, 8), 1.0)
+    }
+
+    #[test]
+    fn nested_screenshots_are_paired_by_relative_path() {
+        let directory = Directory::new("nested");
+        let goldens = directory.0.join("goldens");
+        let actuals = directory.0.join("actuals");
+
+        export::save(&screenshot(0), None, goldens.join("light/button.png")).unwrap();
+        export::save(&screenshot(0), None, actuals.join("light/button.png")).unwrap();
+
+        let cases = compare_directories(&goldens, &actuals, &Tolerance::default()).unwrap();
+
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].name, "light/button.png");
+        assert_eq!(cases[0].status, Status::Passed);
+    }
+
+    #[test]
+    fn missing_screenshots_are_regressions() {
+        let directory = Directory::new("missing");
+        let goldens = directory.0.join("goldens");
+        let actuals = directory.0.join("actuals");
+
+        export::save(&screenshot(0), None, goldens.join("button.png")).unwrap();
+        fs::create_dir_all(&actuals).unwrap();
+
+        let cases = compare_directories(&goldens, &actuals, &Tolerance::default()).unwrap();
+
+        assert_eq!(cases[0].status, Status::Missing);
+        assert_eq!(cases[0].actual, None);
+        assert!(Summary::new(&cases).has_regressions());
+    }
+
+    #[test]
+    fn new_screenshots_are_not_regressions() {
+        let directory = Directory::new("new");
+        let goldens = directory.0.join("goldens");
+        let actuals = directory.0.join("actuals");
+
+        fs::create_dir_all(&goldens).unwrap();
+        export::save(&screenshot(0), None, actuals.join("button.png")).unwrap();
+
+        let cases = compare_directories(&goldens, &actuals, &Tolerance::default()).unwrap();
+
+        assert_eq!(cases[0].status, Status::New);
+        assert_eq!(cases[0].golden, None);
+        assert!(!Summary::new(&cases).has_regressions());
+    }
+
+    #[test]
+    fn approving_removes_pending_files_in_subdirectories() {
+        let directory = Directory::new("approve");
+        let golden = directory.0.join("dark/button.png");
+        let actual = directory.0.join("dark/button.actual.png");
+        let diff = directory.0.join("dark/button.diff.png");
+
+        export::save(&screenshot(0), None, &golden).unwrap();
+        export::save(&screenshot(255), None, &actual).unwrap();
+        export::save(&screenshot(255), None, &diff).unwrap();
+
+        let cases = pending(&directory.0, &Tolerance::default()).unwrap();
+
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].name, "dark/button.png");
+        assert_eq!(cases[0].status, Status::Failed);
+
+        approve(&cases[0], &golden).unwrap();
+
+        let (approved, _) = export::load(&golden).unwrap();
+
+        assert_eq!(approved.bytes, screenshot(255).bytes);
+        assert!(!actual.exists());
+        assert!(!diff.exists());
+    }
+}
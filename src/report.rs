@@ -0,0 +1,238 @@
+//! Generate self-contained HTML reports of screenshot comparisons
+//!
+//! Every image is embedded as a data URI, so a report can be archived or
+//! attached to a CI run as a single file. Each case shows the golden, the
+//! actual screenshot and the diff heatmap side by side, its metrics, and a
+//! slider that either swipes between the golden and the actual screenshot or
+//! fades one over the other.
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::Error;
+use crate::export;
+use crate::regression::{Case, Status, Summary};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; background: #f4f4f4; color: #222; }
+section { background: white; border-radius: 6px; padding: 1em 1.5em; margin-bottom: 2em; }
+h2 { display: flex; gap: 0.5em; align-items: center; font-size: 1.1em; }
+.status { font-size: 0.8em; padding: 0.2em 0.6em; border-radius: 4px; color: white; }
+.passed { background: #2e7d32; } .failed { background: #c62828; }
+.missing { background: #6a1b9a; } .new { background: #1565c0; }
+table { border-collapse: collapse; margin: 0.5em 0 1em; }
+td, th { padding: 0.2em 1em 0.2em 0; text-align: left; }
+.images { display: flex; gap: 1em; flex-wrap: wrap; }
+figure { margin: 0; }
+figure img, .compare img { max-width: 480px; border: 1px solid #ccc; }
+.compare { position: relative; display: inline-block; }
+.compare .top { position: absolute; left: 0; top: 0; clip-path: inset(0 0 0 50%); }
+";
+
+const SCRIPT: &str = "
+function compare(section) {
+    const top = section.querySelector('.compare .top');
+    const value = section.querySelector('.slider').value;
+    const mode = section.querySelector('.mode').value;
+
+    if (mode === 'swipe') {
+        top.style.opacity = 1;
+        top.style.clipPath = `inset(0 0 0 ${value}%)`;
+    } else {
+        top.style.opacity = value / 100;
+        top.style.clipPath = 'none';
+    }
+}
+";
+
+/// Renders the cases as a self-contained HTML page with the given title
+///
+/// Failed cases are listed first.
+pub fn html(title: &str, cases: &[Case]) -> Result<String, Error> {
+    let mut sorted: Vec<&Case> = cases.iter().collect();
+    sorted.sort_by_key(|case| !case.status.is_regression());
+
+    let mut html = String::new();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>{title}</title>\n<style>{STYLE}</style>\n<script>{SCRIPT}</script>\n\
+        </head>\n<body>\n<h1>{title}</h1>\n<p>{summary}</p>\n",
+        title = escape(title),
+        summary = Summary::new(cases.iter()),
+    );
+
+    for case in sorted {
+        section(&mut html, case)?;
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    Ok(html)
+}
+
+/// Writes the HTML report of the cases to the given path
+pub fn write(title: &str, cases: &[Case], path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    let html = html(title, cases)?;
+
+    export::write_file(path, html)
+}
+
+fn section(html: &mut String, case: &Case) -> Result<(), Error> {
+    let (class, label) = match case.status {
+        Status::Passed => ("passed", "Passed"),
+        Status::Failed => ("failed", "Failed"),
+        Status::SizeMismatch { .. } => ("failed", "Size mismatch"),
+        Status::Missing => ("missing", "Missing"),
+        Status::New => ("new", "New"),
+    };
+
+    let _ = write!(
+        html,
+        "<section>\n<h2>{name} <span class=\"status {class}\">{label}</span></h2>\n",
+        name = escape(&case.name),
+    );
+
+    if let Status::SizeMismatch { expected, found } = &case.status {
+        let _ = writeln!(
+            html,
+            "<p>Expected {}x{}, found {}x{}</p>",
+            expected.width, expected.height, found.width, found.height
+        );
+    }
+
+    if let (Some(diff), Some(metrics)) = (&case.diff, &case.metrics) {
+        let _ = write!(
+            html,
+            "<table>\n\
+            <tr><th>Differing pixels</th><td>{} ({:.3}%)</td></tr>\n\
+            <tr><th>Max error</th><td>{}</td></tr>\n\
+            <tr><th>Mean error</th><td>{:.3}</td></tr>\n\
+            <tr><th>PSNR</th><td>{:.2} dB</td></tr>\n\
+            <tr><th>SSIM</th><td>{:.4}</td></tr>\n\
+            <tr><th>MS-SSIM</th><td>{:.4}</td></tr>\n\
+            <tr><th>Mean ΔE</th><td>{:.3}</td></tr>\n\
+            </table>\n",
+            diff.over_threshold,
+            diff.over_threshold_ratio() * 100.0,
+            diff.max_error,
+            diff.mean_error,
+            metrics.psnr,
+            metrics.ssim,
+            metrics.ms_ssim,
+            metrics.mean_delta_e,
+        );
+    }
+
+    let golden = case.golden.as_deref().map(png_file).transpose()?;
+    let actual = case.actual.as_deref().map(png_file).transpose()?;
+    let diff = case
+        .diff
+        .as_ref()
+        .map(|diff| export::encode_png(&diff.heatmap, None).map(|png| data_uri(&png)))
+        .transpose()?;
+
+    html.push_str("<div class=\"images\">\n");
+
+    for (caption, image) in [("Golden", &golden), ("Actual", &actual), ("Diff", &diff)] {
+        if let Some(image) = image {
+            let _ = writeln!(
+                html,
+                "<figure><img src=\"{image}\"><figcaption>{caption}</figcaption></figure>"
+            );
+        }
+    }
+
+    html.push_str("</div>\n");
+
+    let is_comparable = !matches!(case.status, Status::SizeMismatch { .. });
+
+    if let (Some(golden), Some(actual), true) = (&golden, &actual, is_comparable) {
+        let _ = write!(
+            html,
+            "<h3>Compare</h3>\n\
+            <select class=\"mode\" onchange=\"compare(this.closest('section'))\">\
+            <option value=\"swipe\">Swipe</option>\
+            <option value=\"onion\">Onion skin</option></select>\n\
+            <input class=\"slider\" type=\"range\" min=\"0\" max=\"100\" value=\"50\" \
+            oninput=\"compare(this.closest('section'))\"><br>\n\
+            <div class=\"compare\"><img src=\"{golden}\"><img class=\"top\" src=\"{actual}\"></div>\n"
+        );
+    }
+
+    html.push_str("</section>\n");
+
+    Ok(())
+}
+
+fn png_file(path: &Path) -> Result<String, Error> {
+    let bytes = fs::read(path).map_err(|error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    Ok(data_uri(&bytes))
+}
+
+fn data_uri(png: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut uri = String::with_capacity(22 + png.len().div_ceil(3) * 4);
+    uri.push_str("data:image/png;base64,");
+
+    for chunk in png.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+
+        let triple = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                uri.push(char::from(ALPHABET[(triple >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                uri.push('=');
+            }
+        }
+    }
+
+    uri
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_uri_is_base64() {
+        let cases: [(&[u8], &str); 8] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+            (&[0xfb, 0xff, 0xbf], "+/+/"),
+        ];
+
+        for (bytes, base64) in cases {
+            assert_eq!(
+                data_uri(bytes),
+                format!("data:image/png;base64,{base64}"),
+                "{bytes:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Run interaction scripts described in JSON files
+//!
+//! A [`Scenario`] names a view registered in [`Views`], the viewport to
+//! render it in and a list of [`Step`]s to play:
+//!
+//! ```json
+//! {
+//!     "view": "sample",
+//!     "width": 800,
+//!     "height": 600,
+//!     "scale_factor": 2.0,
+//!     "theme": "Dark",
+//!     "steps": [
+//!         { "action": "click", "x": 120, "y": 40 },
+//!         { "action": "type", "text": "Hello\n" },
+//!         { "action": "wait", "millis": 250 },
+//!         { "action": "capture", "name": "greeting" }
+//!     ]
+//! }
+//! ```
+use iced::advanced::renderer;
+use iced::advanced::renderer::Headless;
+use iced::{Element, Point, Size, Theme, Vector, window};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::Error;
+use crate::export;
+use crate::simulator::Simulator;
+
+/// A script of interactions with a registered view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// The name of the view in [`Views`]
+    pub view: String,
+    /// The logical width of the viewport
+    pub width: f32,
+    /// The logical height of the viewport
+    pub height: f32,
+    /// The scale factor of the viewport
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f32,
+    /// The name of the built-in theme to render with, if not the current one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// The steps to play, in order
+    pub steps: Vec<Step>,
+}
+
+fn default_scale_factor() -> f32 {
+    1.0
+}
+
+/// A step of a [`Scenario`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    /// Moves the cursor to a position
+    MoveCursor {
+        /// The horizontal position
+        x: f32,
+        /// The vertical position
+        y: f32,
+    },
+    /// Clicks the left mouse button at a position
+    Click {
+        /// The horizontal position
+        x: f32,
+        /// The vertical position
+        y: f32,
+    },
+    /// Scrolls the mouse wheel by some pixels
+    Scroll {
+        /// The horizontal amount
+        #[serde(default)]
+        x: f32,
+        /// The vertical amount
+        #[serde(default)]
+        y: f32,
+    },
+    /// Types some text, with `\n` pressing Enter
+    Type {
+        /// The text to type
+        text: String,
+    },
+    /// Advances the clock of the simulator and redraws
+    Wait {
+        /// The time to wait, in milliseconds
+        millis: u64,
+    },
+    /// Takes a screenshot
+    Capture {
+        /// The name of the capture, used as its file name
+        ///
+        /// It must be unique within the scenario and cannot contain path
+        /// separators.
+        name: String,
+    },
+}
+
+/// A screenshot taken by a [`Step::Capture`]
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// The name of the capture
+    pub name: String,
+    /// The screenshot
+    pub screenshot: window::Screenshot,
+}
+
+type View<'a, R> = Box<dyn Fn() -> Element<'a, (), Theme, R> + 'a>;
+
+/// The views that scenarios can refer to, by name
+pub struct Views<'a, R = iced::Renderer> {
+    views: BTreeMap<String, View<'a, R>>,
+}
+
+impl<'a, R> Views<'a, R>
+where
+    R: renderer::Renderer + 'a,
+{
+    /// Creates an empty set of [`Views`]
+    pub fn new() -> Self {
+        Self {
+            views: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a view under the given name
+    ///
+    /// The messages produced by the view are discarded.
+    pub fn register<Message>(
+        mut self,
+        name: impl Into<String>,
+        view: impl Fn() -> Element<'a, Message, Theme, R> + 'a,
+    ) -> Self
+    where
+        Message: 'a,
+    {
+        let _ = self
+            .views
+            .insert(name.into(), Box::new(move || view().map(|_| ())));
+
+        self
+    }
+
+    /// Returns the names of the registered views, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.views.keys().map(String::as_str)
+    }
+
+    /// Builds the view registered under the given name
+    pub fn view(&self, name: &str) -> Option<Element<'a, (), Theme, R>> {
+        self.views.get(name).map(|view| view())
+    }
+}
+
+impl<'a, R> Default for Views<'a, R>
+where
+    R: renderer::Renderer + 'a,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Finds the built-in theme with the given name, like `"Dark"` or `"Tokyo Night"`
+pub fn theme(name: &str) -> Result<Theme, Error> {
+    Theme::ALL
+        .iter()
+        .find(|theme| theme.to_string() == name)
+        .cloned()
+        .ok_or_else(|| Error::UnknownTheme(name.to_owned()))
+}
+
+impl Scenario {
+    /// Parses a [`Scenario`] from JSON
+    ///
+    /// Fails if a capture name is not a plain file name or is repeated.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let scenario: Self =
+            serde_json::from_str(json).map_err(|e| Error::Scenario(e.to_string()))?;
+
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+
+    // Ensures every capture can be written to its own file in the output directory
+    fn validate(&self) -> Result<(), Error> {
+        let mut names = BTreeSet::new();
+
+        for step in &self.steps {
+            let Step::Capture { name } = step else {
+                continue;
+            };
+
+            if name.is_empty()
+                || name == "."
+                || name == ".."
+                || name.contains(['/', '\\'])
+                || Path::new(name).is_absolute()
+            {
+                return Err(Error::Scenario(format!("invalid capture name {name:?}")));
+            }
+
+            if !names.insert(name.as_str()) {
+                return Err(Error::Scenario(format!("duplicate capture name {name:?}")));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a [`Scenario`] from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        let json = fs::read_to_string(path).map_err(|error| Error::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        Self::from_json(&json)
+    }
+
+    /// Plays the [`Scenario`] and returns its captures, in order
+    ///
+    /// The theme of the [`Simulator`] is restored afterwards.
+    pub fn run<'a, R>(
+        &self,
+        simulator: &mut Simulator<R>,
+        views: &Views<'a, R>,
+    ) -> Result<Vec<Capture>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+    {
+        self.with_theme(simulator, |simulator| self.play(simulator, views))
+    }
+
+    /// Plays the [`Scenario`] and writes each capture as a PNG named after it
+    /// in the given directory, returning the written paths
+    pub fn run_to<'a, R>(
+        &self,
+        simulator: &mut Simulator<R>,
+        views: &Views<'a, R>,
+        directory: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+    {
+        let directory = directory.as_ref();
+
+        self.with_theme(simulator, |simulator| {
+            self.play(simulator, views)?
+                .into_iter()
+                .map(|capture| {
+                    let path = directory.join(format!("{}.png", capture.name));
+                    let metadata = simulator.metadata(&capture.screenshot);
+
+                    export::save(&capture.screenshot, Some(&metadata), &path)?;
+
+                    Ok(path)
+                })
+                .collect()
+        })
+    }
+
+    fn with_theme<R, T>(
+        &self,
+        simulator: &mut Simulator<R>,
+        f: impl FnOnce(&mut Simulator<R>) -> Result<T, Error>,
+    ) -> Result<T, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+    {
+        match &self.theme {
+            Some(name) => simulator.in_theme(theme(name)?, f),
+            None => f(simulator),
+        }
+    }
+
+    fn play<'a, R>(
+        &self,
+        simulator: &mut Simulator<R>,
+        views: &Views<'a, R>,
+    ) -> Result<Vec<Capture>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+    {
+        self.validate()?;
+
+        let view = || {
+            views
+                .view(&self.view)
+                .ok_or_else(|| Error::UnknownView(self.view.clone()))
+        };
+
+        let mut session = simulator.session(Size::new(self.width, self.height), self.scale_factor);
+        let mut captures = Vec::new();
+
+        for step in &self.steps {
+            match step {
+                Step::MoveCursor { x, y } => {
+                    let _ = session.interact(view()?, |interface| {
+                        interface.move_cursor(Point::new(*x, *y))
+                    });
+                }
+                Step::Click { x, y } => {
+                    let _ =
+                        session.interact(view()?, |interface| interface.click(Point::new(*x, *y)));
+                }
+                Step::Scroll { x, y } => {
+                    let _ = session
+                        .interact(view()?, |interface| interface.scroll(Vector::new(*x, *y)));
+                }
+                Step::Type { text } => {
+                    let _ = session.interact(view()?, |interface| interface.type_text(text));
+                }
+                Step::Wait { millis } => {
+                    let _ = session.interact(view()?, |interface| {
+                        interface.advance(Duration::from_millis(*millis))
+                    });
+                }
+                Step::Capture { name } => captures.push(Capture {
+                    name: name.clone(),
+                    screenshot: session.screenshot(view()?)?,
+                }),
+            }
+        }
+
+        Ok(captures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(captures: &[&str]) -> String {
+        let steps: Vec<String> = captures
+            .iter()
+            .map(|name| format!(r#"{{ "action": "capture", "name": {name:?} }}"#))
+            .collect();
+
+        format!(
+            r#"{{ "view": "sample", "width": 100, "height": 100, "steps": [{}] }}"#,
+            steps.join(", ")
+        )
+    }
+
+    #[test]
+    fn accepts_unique_file_names() {
+        let scenario = Scenario::from_json(&scenario(&["first", "second.v2"])).unwrap();
+
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(scenario.scale_factor, 1.0);
+    }
+
+    #[test]
+    fn rejects_paths() {
+        for name in ["../x", "/tmp/x", "a/b", "a\\b", "..", ""] {
+            assert!(
+                Scenario::from_json(&scenario(&[name])).is_err(),
+                "{name:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        assert!(Scenario::from_json(&scenario(&["same", "same"])).is_err());
+    }
+}
@@ -1,15 +1,55 @@
 //! Simulate `iced` user interfaces and take screenshots of them
+use iced::advanced::clipboard;
+use iced::advanced::layout::{self, Layout};
 use iced::advanced::renderer;
 use iced::advanced::renderer::Headless;
+use iced::advanced::text;
+use iced::advanced::widget::{self, Operation, operation};
+use iced::futures::channel::oneshot;
+use iced::keyboard::{self, key};
 use iced::theme::Base;
-use iced::{self, Element, Size, mouse, window};
+use iced::time::Instant;
+use iced::widget::Container;
+use iced::{self, Color, Element, Event, Padding, Point, Rectangle, Size, Vector, mouse, window};
 use iced_runtime::{UserInterface, user_interface};
-use std::borrow::Cow;
-use std::env;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
 
 use iced::Theme;
 
+use crate::Error;
+use crate::diff::{self, Comparison};
+use crate::dump::Dump;
+use crate::export::Metadata;
+use crate::font::{self, Fonts, MissingGlyph};
+use crate::lint::{self, Checks, Issue};
+use crate::recording::Recording;
+
+mod backend;
+mod query;
+mod root;
+mod runner;
+
+pub use backend::Backend;
+pub use query::{Kind, Match, Selector};
+pub use runner::Runner;
+
+use root::Root;
+
+// The width of the default vertical scrollbar of a `scrollable`
+const SCROLLBAR_WIDTH: f32 = 10.0;
+
 /// A simulator that can take screenshots of `iced`` user interfaces
+///
+/// # Blocking
+/// The synchronous constructors block the current thread until the renderer
+/// is ready, and screenshots are drawn on the calling thread. Inside of an
+/// async runtime, create it with [`Simulator::try_new_async`] or
+/// [`Builder::build_async`], and draw with [`Simulator::screenshot_async`] or
+/// [`Simulator::run_async`], which move the simulator to a thread of its own.
+/// Use a [`Pool`](crate::pool::Pool) to draw several views in parallel.
 pub struct Simulator<R = iced::Renderer>
 where
     R: renderer::Renderer + Headless + Send,
@@ -17,12 +57,63 @@ where
     renderer: R,
     cursor: mouse::Cursor,
     theme: Theme,
+    background: Background,
+    default_font: iced::Font,
+    clock: Instant,
+}
+
+/// The color that screenshots are cleared with before drawing
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Background {
+    /// The background color of the current [`Theme`]
+    #[default]
+    Theme,
+    /// A fully transparent background
+    Transparent,
+    /// A custom color
+    Color(Color),
+}
+
+impl Background {
+    /// Returns the color of the [`Background`] in the given [`Theme`]
+    pub fn color(self, theme: &Theme) -> Color {
+        match self {
+            Background::Theme => theme.base().background_color,
+            Background::Transparent => Color::TRANSPARENT,
+            Background::Color(color) => color,
+        }
+    }
+}
+
+impl From<Color> for Background {
+    fn from(color: Color) -> Self {
+        Background::Color(color)
+    }
+}
+
+/// How [`Simulator::screenshot_full_page`] captures content taller than the viewport
+#[derive(Debug, Clone, PartialEq)]
+pub enum FullPage {
+    /// Scrolls through the `scrollable` with the given id, stitching its
+    /// segments into one tall image of its whole content
+    Scroll(widget::Id),
+    /// Lays out the element with an unbounded height
+    ///
+    /// Content inside of a `scrollable` stays clipped, so this suits views
+    /// that are only wrapped in a `scrollable` by the application.
+    Unbounded,
 }
 
 impl<R> Simulator<R>
 where
-    R: renderer::Renderer + Headless + Send,
+    R: renderer::Renderer + Headless + Send + 'static,
 {
+    /// Creates a new [`Simulator`] with the default settings
+    ///
+    /// This blocks until the renderer is ready; see [blocking](Simulator#blocking).
+    ///
+    /// # Panics
+    /// Panics if the simulator cannot be created. See [`Simulator::try_new`].
     pub fn new() -> Self
     where
         R: Headless,
@@ -30,89 +121,1097 @@ where
         Self::with_size(iced::Settings::default())
     }
 
+    /// Creates a new [`Simulator`] with the given settings
+    ///
+    /// # Panics
+    /// Panics if the simulator cannot be created. See [`Simulator::try_with_settings`].
     pub fn with_size(settings: iced::Settings) -> Self
     where
         R: Headless,
     {
-        let default_font = match settings.default_font {
-            iced::Font::DEFAULT => iced::Font::with_name("Fira Sans"),
-            _ => settings.default_font,
-        };
+        Self::try_with_settings(settings).unwrap_or_else(|error| panic!("{error}"))
+    }
 
-        for font in settings.fonts {
-            load_font(font).expect("Font must be valid");
+    /// Creates a new [`Simulator`] with the default settings
+    ///
+    /// This blocks until the renderer is ready; see [blocking](Simulator#blocking).
+    pub fn try_new() -> Result<Self, Error>
+    where
+        R: Headless,
+    {
+        Self::try_with_settings(iced::Settings::default())
+    }
+
+    /// Creates a new [`Simulator`] with the given settings
+    ///
+    /// This blocks until the renderer is ready; see [blocking](Simulator#blocking).
+    pub fn try_with_settings(settings: iced::Settings) -> Result<Self, Error>
+    where
+        R: Headless,
+    {
+        iced::futures::executor::block_on(Self::create(settings, Backend::Default, Fonts::new()))
+    }
+
+    /// Creates a new [`Simulator`] with the default settings, without blocking
+    /// the executor while the renderer is created
+    pub async fn try_new_async() -> Result<Self, Error>
+    where
+        R: Headless,
+    {
+        Self::try_with_settings_async(iced::Settings::default()).await
+    }
+
+    /// Creates a new [`Simulator`] with the given settings, without blocking
+    /// the executor while the renderer is created
+    pub async fn try_with_settings_async(settings: iced::Settings) -> Result<Self, Error>
+    where
+        R: Headless,
+    {
+        Self::create(settings, Backend::Default, Fonts::new()).await
+    }
+
+    async fn create(
+        settings: iced::Settings,
+        backend: Backend,
+        fonts: Fonts,
+    ) -> Result<Self, Error> {
+        for bytes in settings.fonts {
+            font::load(bytes)?;
         }
 
+        fonts.load()?;
+
+        let default_font = fonts.default_font(settings.default_font)?;
+
         let renderer = {
-            let backend = env::var("ICED_BACKEND").ok();
-
-            iced::futures::executor::block_on(R::new(
-                default_font,
-                settings.default_text_size,
-                backend.as_deref(),
-            ))
-            .expect("Create new headless renderer")
+            let backend = backend.name();
+
+            R::new(default_font, settings.default_text_size, backend.as_deref())
+                .await
+                .ok_or(Error::RendererCreation { backend })?
         };
 
-        Simulator {
+        Ok(Simulator {
             renderer,
             cursor: mouse::Cursor::Unavailable,
             theme: Theme::default(),
+            background: Background::default(),
+            default_font,
+            clock: Instant::now(),
+        })
+    }
+
+    /// Returns the font used by text widgets that do not set one
+    pub fn default_font(&self) -> iced::Font {
+        self.default_font
+    }
+
+    /// Takes a screenshot of the view on a thread of its own, without
+    /// blocking the executor
+    ///
+    /// The [`Simulator`] is handed back along with the screenshot. See
+    /// [`Simulator::run_async`].
+    pub async fn screenshot_async<Message>(
+        self,
+        view: impl FnOnce() -> Element<'static, Message, Theme, R> + Send + 'static,
+        size: impl Into<Size>,
+        scale_factor: f32,
+    ) -> (Self, Result<window::Screenshot, Error>)
+    where
+        R: 'static,
+        Message: 'static,
+    {
+        let size = size.into();
+
+        self.run_async(move |simulator| simulator.screenshot(view(), size, scale_factor))
+            .await
+    }
+
+    /// Moves the [`Simulator`] to a thread of its own and runs the closure
+    /// there, without blocking the executor
+    ///
+    /// The [`Simulator`] is handed back along with the output of the closure.
+    ///
+    /// # Panics
+    /// Panics if the closure panics.
+    pub async fn run_async<T>(
+        mut self,
+        f: impl FnOnce(&mut Self) -> T + Send + 'static,
+    ) -> (Self, T)
+    where
+        R: 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        let _ = thread::spawn(move || {
+            let output = f(&mut self);
+            let _ = sender.send((self, output));
+        });
+
+        receiver.await.expect("Run simulator on its own thread")
+    }
+
+    /// Returns the name of the renderer backend that is actually in use
+    pub fn backend(&self) -> String {
+        self.renderer.name()
+    }
+
+    /// Returns the [`Metadata`] describing a screenshot taken by this [`Simulator`]
+    pub fn metadata(&self, screenshot: &window::Screenshot) -> Metadata {
+        Metadata {
+            backend: Some(self.backend()),
+            theme: Some(self.theme.to_string()),
+            fonts: vec![family_name(self.default_font.family)],
+            ..Metadata::new(screenshot)
         }
     }
 
-    // Takes a screenshot of the given element
-    pub fn screenshot<'a, Message>(
+    /// Sets the [`Theme`] used to draw user interfaces
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets the [`Theme`] used to draw user interfaces
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Returns the current time of the clock of the [`Simulator`]
+    ///
+    /// The clock starts when the [`Simulator`] is created and only moves
+    /// forward when it is advanced.
+    pub fn now(&self) -> Instant {
+        self.clock
+    }
+
+    /// Moves the clock of the [`Simulator`] forward
+    pub fn advance(&mut self, duration: Duration) {
+        self.clock += duration;
+    }
+
+    /// Returns the [`Theme`] used to draw user interfaces
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Runs the closure with the given [`Theme`], restoring the current one
+    /// afterwards
+    pub fn in_theme<T>(&mut self, theme: Theme, f: impl FnOnce(&mut Self) -> T) -> T {
+        let original = std::mem::replace(&mut self.theme, theme);
+        let result = f(self);
+
+        self.theme = original;
+
+        result
+    }
+
+    /// Sets the [`Background`] that screenshots are cleared with
+    ///
+    /// Screenshots taken on a background that is not opaque keep the alpha
+    /// of every pixel, unpremultiplied, so they can be composited elsewhere.
+    pub fn set_background(&mut self, background: impl Into<Background>) {
+        self.background = background.into();
+    }
+
+    /// Returns the [`Background`] that screenshots are cleared with
+    pub fn background(&self) -> Background {
+        self.background
+    }
+
+    /// Builds an [`Interface`] for the given element, ready to receive events
+    ///
+    /// The element is laid out in the given logical size; the scale factor
+    /// only affects the physical size of screenshots.
+    pub fn build<'a, Message>(
         &mut self,
         element: impl Into<Element<'a, Message, Theme, R>>,
         size: impl Into<Size>,
         scale_factor: f32,
-    ) -> Result<window::Screenshot, String>
+    ) -> Interface<'a, '_, Message, R>
+    where
+        Message: 'a,
+    {
+        self.build_with_cache(
+            element,
+            size.into(),
+            scale_factor,
+            user_interface::Cache::default(),
+        )
+    }
+
+    /// Measures the size of the element when laid out within the given maximum size
+    ///
+    /// The size is rounded up to whole logical pixels. Use [`Size::INFINITE`]
+    /// to lay out the element without constraints; elements that fill the
+    /// available space then fail to be measured.
+    pub fn measure<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        max: impl Into<Size>,
+    ) -> Result<Size, Error>
+    where
+        Message: 'a,
+    {
+        self.measure_element(&mut element.into(), max.into())
+    }
+
+    /// Takes a screenshot of exactly the bounds of the element, plus some padding
+    ///
+    /// The element is laid out within the given maximum size, which may be
+    /// [`Size::INFINITE`]. See [`Simulator::measure`].
+    pub fn screenshot_content<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        max: impl Into<Size>,
+        padding: impl Into<Padding>,
+        scale_factor: f32,
+    ) -> Result<window::Screenshot, Error>
+    where
+        Message: 'a,
+    {
+        let mut element = Element::from(Container::new(element).padding(padding));
+        let size = self.measure_element(&mut element, max.into())?;
+
+        self.screenshot(element, size, scale_factor)
+    }
+
+    /// Takes a screenshot of the element including the content that does not
+    /// fit in the viewport, as described by the [`FullPage`] mode
+    pub fn screenshot_full_page<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: impl Into<Size>,
+        scale_factor: f32,
+        mode: FullPage,
+    ) -> Result<window::Screenshot, Error>
     where
         Message: 'a,
     {
-        let base = self.theme.base();
         let size = size.into();
 
+        match mode {
+            FullPage::Scroll(id) => self
+                .build(element, size, scale_factor)
+                .screenshot_full_page(id),
+            FullPage::Unbounded => {
+                let mut element = element.into();
+                let content =
+                    self.measure_element(&mut element, Size::new(size.width, f32::INFINITY))?;
+
+                self.screenshot(
+                    element,
+                    Size::new(size.width, content.height.max(size.height)),
+                    scale_factor,
+                )
+            }
+        }
+    }
+
+    fn measure_element<Message>(
+        &mut self,
+        element: &mut Element<'_, Message, Theme, R>,
+        max: Size,
+    ) -> Result<Size, Error> {
+        let mut tree = widget::Tree::new(element.as_widget());
+
+        let node = element.as_widget_mut().layout(
+            &mut tree,
+            &self.renderer,
+            &layout::Limits::new(Size::ZERO, max),
+        );
+
+        let size = node.size();
+
+        if !(size.width.is_finite() && size.height.is_finite()) {
+            return Err(Error::Measure { max, size });
+        }
+
+        Ok(Size::new(size.width.ceil(), size.height.ceil()))
+    }
+
+    /// Takes a screenshot of the region covered by the first widget of the
+    /// element matched by the [`Selector`]
+    pub fn screenshot_region<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: impl Into<Size>,
+        scale_factor: f32,
+        selector: impl Into<Selector>,
+    ) -> Result<window::Screenshot, Error>
+    where
+        Message: 'a,
+    {
+        self.build(element, size, scale_factor)
+            .screenshot_region(selector)
+    }
+
+    /// Dumps the layout tree of the element laid out in the given size
+    ///
+    /// See [`Dump::from_layout`].
+    pub fn dump<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: impl Into<Size>,
+    ) -> Dump
+    where
+        Message: 'a,
+    {
+        self.build(element, size, 1.0).dump()
+    }
+
+    /// Checks the element laid out in the given size for layout mistakes
+    ///
+    /// See the [`lint`] module for the details of each check.
+    pub fn lint<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: impl Into<Size>,
+        checks: &Checks,
+    ) -> Vec<Issue>
+    where
+        Message: 'a,
+        R: text::Renderer,
+        R::Paragraph: 'static,
+    {
+        let size = size.into();
+        let mut element = element.into();
+        let mut tree = widget::Tree::new(element.as_widget());
+
+        let node = element.as_widget_mut().layout(
+            &mut tree,
+            &self.renderer,
+            &layout::Limits::new(Size::ZERO, size),
+        );
+
+        let mut collect = query::Collect::default();
+
+        element.as_widget_mut().operate(
+            &mut tree,
+            Layout::new(&node),
+            &self.renderer,
+            &mut collect,
+        );
+
+        let dump = Dump::from_layout(size, Layout::new(&node), collect.matches);
+        let mut issues = lint::check(&dump, checks);
+
+        if checks.clipped_text {
+            lint::check_text::<R::Paragraph>(
+                &tree,
+                Layout::new(&node),
+                checks.tolerance,
+                &mut issues,
+            );
+        }
+
+        issues
+    }
+
+    /// Lays out the element in the given size and returns the characters of
+    /// its `text` widgets that no loaded font can draw
+    ///
+    /// See the [`font`] module for loading fonts that cover them.
+    pub fn missing_glyphs<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: impl Into<Size>,
+    ) -> Vec<MissingGlyph>
+    where
+        Message: 'a,
+        R: text::Renderer<Paragraph = iced::advanced::graphics::text::Paragraph>,
+    {
+        let mut element = element.into();
+        let mut tree = widget::Tree::new(element.as_widget());
+
+        let _ = element.as_widget_mut().layout(
+            &mut tree,
+            &self.renderer,
+            &layout::Limits::new(Size::ZERO, size.into()),
+        );
+
+        let mut missing = Vec::new();
+        font::find_missing(&tree, &mut missing);
+
+        missing
+    }
+
+    /// Takes a screenshot of the view in each of the given themes, labeled by theme
+    ///
+    /// The theme of the [`Simulator`] is restored afterwards.
+    pub fn screenshot_themes<'a, Message, E>(
+        &mut self,
+        themes: impl IntoIterator<Item = Theme>,
+        view: impl Fn() -> E,
+        size: impl Into<Size>,
+        scale_factor: f32,
+    ) -> Result<Vec<(Theme, window::Screenshot)>, Error>
+    where
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        let size = size.into();
+
+        themes
+            .into_iter()
+            .map(|theme| {
+                self.in_theme(theme.clone(), |simulator| {
+                    simulator.screenshot(view(), size, scale_factor)
+                })
+                .map(|screenshot| (theme, screenshot))
+            })
+            .collect()
+    }
+
+    /// Takes a screenshot of the view in every built-in theme (see [`Theme::ALL`])
+    pub fn screenshot_all_themes<'a, Message, E>(
+        &mut self,
+        view: impl Fn() -> E,
+        size: impl Into<Size>,
+        scale_factor: f32,
+    ) -> Result<Vec<(Theme, window::Screenshot)>, Error>
+    where
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        self.screenshot_themes(Theme::ALL.iter().cloned(), view, size, scale_factor)
+    }
+
+    /// Takes a screenshot of the view with this and another [`Simulator`],
+    /// typically configured with a different [`Backend`], and compares them
+    pub fn compare_backends<'a, Message, E>(
+        &mut self,
+        other: &mut Simulator<R>,
+        view: impl Fn() -> E,
+        size: impl Into<Size>,
+        scale_factor: f32,
+        threshold: u8,
+    ) -> Result<Comparison, Error>
+    where
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        let size = size.into();
+
+        let first = self.screenshot(view(), size, scale_factor)?;
+        let second = other.screenshot(view(), size, scale_factor)?;
+
+        let diff = diff::diff(&first, &second, threshold)?;
+
+        Ok(Comparison {
+            backends: (self.backend(), other.backend()),
+            screenshots: (first, second),
+            diff,
+        })
+    }
+
+    /// Starts a [`Session`] that keeps widget state across builds of a view
+    pub fn session(&mut self, size: impl Into<Size>, scale_factor: f32) -> Session<'_, R> {
+        Session {
+            simulator: self,
+            cache: user_interface::Cache::default(),
+            size: size.into(),
+            scale_factor,
+        }
+    }
+
+    fn build_with_cache<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: Size,
+        scale_factor: f32,
+        cache: user_interface::Cache,
+    ) -> Interface<'a, '_, Message, R>
+    where
+        Message: 'a,
+    {
+        let (root, layout) = Root::new(element.into());
+
+        // Layout happens in logical pixels, like in a real window
+        let ui = UserInterface::build(root, size, cache, &mut self.renderer);
+
+        Interface {
+            simulator: self,
+            ui,
+            layout,
+            size,
+            scale_factor,
+        }
+    }
+
+    // Takes a screenshot of the given element
+    pub fn screenshot<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        size: impl Into<Size>,
+        scale_factor: f32,
+    ) -> Result<window::Screenshot, Error>
+    where
+        Message: 'a,
+    {
         // build a UI just for this screenshot
-        let mut ui = UserInterface::build(
-            element,
-            size * scale_factor,
-            user_interface::Cache::default(),
-            &mut self.renderer,
+        self.build(element, size, scale_factor).screenshot()
+    }
+}
+
+impl Simulator {
+    /// Returns a [`Builder`] to configure a new [`Simulator`]
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+/// A builder of a [`Simulator`]
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    settings: iced::Settings,
+    backend: Backend,
+    fonts: Fonts,
+}
+
+impl Builder {
+    /// Sets the [`iced::Settings`] of the [`Simulator`]
+    pub fn settings(mut self, settings: iced::Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the renderer [`Backend`] of the [`Simulator`]
+    ///
+    /// Building fails if the requested backend is not available.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the [`Fonts`] loaded before the [`Simulator`] is built
+    ///
+    /// Building fails if a font cannot be read or none of the
+    /// [default family candidates](Fonts::default_family_candidates) is loaded.
+    pub fn fonts(mut self, fonts: Fonts) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    /// Builds the [`Simulator`]
+    ///
+    /// This blocks until the renderer is ready; see [blocking](Simulator#blocking).
+    pub fn build<R>(self) -> Result<Simulator<R>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+    {
+        iced::futures::executor::block_on(self.build_async())
+    }
+
+    /// Builds the [`Simulator`] without blocking the executor while the
+    /// renderer is created
+    pub async fn build_async<R>(self) -> Result<Simulator<R>, Error>
+    where
+        R: renderer::Renderer + Headless + Send + 'static,
+    {
+        Simulator::create(self.settings, self.backend, self.fonts).await
+    }
+}
+
+/// A sequence of builds of the same view that shares widget state and layout caches
+///
+/// Use [`Session::interact`] to feed events to the current view and
+/// [`Session::screenshot`] to capture it; scroll offsets, focus and text
+/// cursors are preserved between calls.
+pub struct Session<'s, R = iced::Renderer>
+where
+    R: renderer::Renderer + Headless + Send,
+{
+    simulator: &'s mut Simulator<R>,
+    cache: user_interface::Cache,
+    size: Size,
+    scale_factor: f32,
+}
+
+impl<R> Session<'_, R>
+where
+    R: renderer::Renderer + Headless + Send + 'static,
+{
+    /// Builds the element with the cached state and runs the given closure on it
+    pub fn interact<'a, Message, T>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+        f: impl FnOnce(&mut Interface<'a, '_, Message, R>) -> T,
+    ) -> T
+    where
+        Message: 'a,
+    {
+        let cache = std::mem::take(&mut self.cache);
+
+        let mut interface =
+            self.simulator
+                .build_with_cache(element, self.size, self.scale_factor, cache);
+
+        let output = f(&mut interface);
+
+        self.cache = interface.ui.into_cache();
+
+        output
+    }
+
+    /// Builds the element with the cached state and takes a screenshot of it
+    pub fn screenshot<'a, Message>(
+        &mut self,
+        element: impl Into<Element<'a, Message, Theme, R>>,
+    ) -> Result<window::Screenshot, Error>
+    where
+        Message: 'a,
+    {
+        self.interact(element, |interface| interface.screenshot())
+    }
+
+    /// Moves the clock of the [`Simulator`] forward
+    pub fn advance(&mut self, duration: Duration) {
+        self.simulator.advance(duration);
+    }
+
+    /// Records the view over time, redrawing it before each frame
+    ///
+    /// The clock is advanced by the interval between two frames, so
+    /// animations play exactly as they would at that frame rate.
+    pub fn record<'a, Message, E>(
+        &mut self,
+        view: impl Fn() -> E,
+        frames: usize,
+        interval: Duration,
+    ) -> Result<Recording, Error>
+    where
+        Message: 'a,
+        E: Into<Element<'a, Message, Theme, R>>,
+    {
+        let mut recording = Recording {
+            frames: Vec::with_capacity(frames),
+            interval,
+        };
+
+        for frame in 0..frames {
+            if frame > 0 {
+                self.advance(interval);
+            }
+
+            let screenshot = self.interact(view(), |interface| {
+                let _ = interface.redraw();
+
+                interface.screenshot()
+            })?;
+
+            recording.frames.push(screenshot);
+        }
+
+        Ok(recording)
+    }
+
+    /// Discards all the widget state kept by the [`Session`]
+    pub fn reset(&mut self) {
+        self.cache = user_interface::Cache::default();
+    }
+
+    /// Returns the logical size of the viewport of the [`Session`]
+    pub fn size(&self) -> Size {
+        self.size
+    }
+}
+
+/// A user interface built by a [`Simulator`] that can be driven with events
+pub struct Interface<'a, 's, Message, R = iced::Renderer>
+where
+    R: renderer::Renderer + Headless + Send,
+{
+    simulator: &'s mut Simulator<R>,
+    ui: UserInterface<'a, Message, Theme, R>,
+    layout: Rc<RefCell<layout::Node>>,
+    size: Size,
+    scale_factor: f32,
+}
+
+impl<Message, R> Interface<'_, '_, Message, R>
+where
+    R: renderer::Renderer + Headless + Send + 'static,
+{
+    /// Feeds the given events to the user interface, returning the produced messages
+    pub fn simulate(&mut self, events: impl IntoIterator<Item = Event>) -> Vec<Message> {
+        let events: Vec<Event> = events.into_iter().collect();
+        let mut messages = Vec::new();
+
+        let _ = self.ui.update(
+            &events,
+            self.simulator.cursor,
+            &mut self.simulator.renderer,
+            &mut clipboard::Null,
+            &mut messages,
         );
 
-        let _ = ui.draw(
-            &mut self.renderer,
-            &self.theme,
+        messages
+    }
+
+    /// Requests a redraw at the current time of the clock of the [`Simulator`]
+    pub fn redraw(&mut self) -> Vec<Message> {
+        self.redraw_at(self.simulator.clock)
+    }
+
+    /// Requests a redraw at the given time, letting animated widgets catch up to it
+    pub fn redraw_at(&mut self, now: Instant) -> Vec<Message> {
+        self.simulate([Event::Window(window::Event::RedrawRequested(now))])
+    }
+
+    /// Moves the clock of the [`Simulator`] forward and requests a redraw
+    pub fn advance(&mut self, duration: Duration) -> Vec<Message> {
+        self.simulator.advance(duration);
+
+        self.redraw()
+    }
+
+    /// Moves the cursor to the given position
+    pub fn move_cursor(&mut self, position: impl Into<Point>) -> Vec<Message> {
+        let position = position.into();
+        self.simulator.cursor = mouse::Cursor::Available(position);
+
+        self.simulate([Event::Mouse(mouse::Event::CursorMoved { position })])
+    }
+
+    /// Moves the cursor to the given position and clicks the left mouse button
+    pub fn click(&mut self, position: impl Into<Point>) -> Vec<Message> {
+        let mut messages = self.move_cursor(position);
+
+        messages.extend(self.simulate([
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)),
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)),
+        ]));
+
+        messages
+    }
+
+    /// Scrolls the mouse wheel by the given amount of pixels at the current cursor position
+    pub fn scroll(&mut self, delta: impl Into<Vector>) -> Vec<Message> {
+        let delta = delta.into();
+
+        self.simulate([Event::Mouse(mouse::Event::WheelScrolled {
+            delta: mouse::ScrollDelta::Pixels {
+                x: delta.x,
+                y: delta.y,
+            },
+        })])
+    }
+
+    /// Types the given text, one key press at a time
+    pub fn type_text(&mut self, text: &str) -> Vec<Message> {
+        let events = text.chars().flat_map(|c| {
+            let (key, text) = match c {
+                '\n' => (keyboard::Key::Named(key::Named::Enter), None),
+                _ => (
+                    keyboard::Key::Character(c.to_string().into()),
+                    Some(c.to_string().into()),
+                ),
+            };
+
+            [press_key(key.clone(), text), release_key(key)]
+        });
+
+        self.simulate(events)
+    }
+
+    /// Applies the given widget operation, following any chained operations
+    pub fn operate(&mut self, operation: Box<dyn Operation>) {
+        let mut current = Some(operation);
+
+        while let Some(mut operation) = current.take() {
+            self.ui
+                .operate(&self.simulator.renderer, operation.as_mut());
+
+            if let operation::Outcome::Chain(next) = operation.finish() {
+                current = Some(next);
+            }
+        }
+    }
+
+    /// Returns every widget reported by the widget operations of the user interface,
+    /// in traversal order
+    pub fn widgets(&mut self) -> Vec<Match> {
+        let mut collect = query::Collect::default();
+
+        self.ui.operate(&self.simulator.renderer, &mut collect);
+
+        collect.matches
+    }
+
+    /// Finds the first widget matched by the [`Selector`]
+    pub fn find(&mut self, selector: impl Into<Selector>) -> Option<Match> {
+        let selector = selector.into();
+
+        self.widgets()
+            .into_iter()
+            .find(|candidate| selector.matches(candidate))
+    }
+
+    /// Finds every widget matched by the [`Selector`]
+    pub fn find_all(&mut self, selector: impl Into<Selector>) -> Vec<Match> {
+        let selector = selector.into();
+
+        self.widgets()
+            .into_iter()
+            .filter(|candidate| selector.matches(candidate))
+            .collect()
+    }
+
+    /// Dumps the layout tree of the user interface in its current state
+    ///
+    /// See [`Dump::from_layout`].
+    pub fn dump(&mut self) -> Dump {
+        let widgets = self.widgets();
+        let layout = self.layout.borrow();
+
+        Dump::from_layout(self.size, Layout::new(&layout), widgets)
+    }
+
+    /// Takes a screenshot of the region covered by the first widget matched by the [`Selector`]
+    pub fn screenshot_region(
+        &mut self,
+        selector: impl Into<Selector>,
+    ) -> Result<window::Screenshot, Error> {
+        let selector = selector.into();
+
+        let bounds = self
+            .find(selector.clone())
+            .ok_or(Error::NotFound(selector))?
+            .bounds;
+
+        let screenshot = self.screenshot()?;
+
+        crop(&screenshot, bounds)
+    }
+
+    /// Takes a screenshot of the whole content of the `scrollable` with the
+    /// given id, scrolling through it and stitching the segments vertically
+    ///
+    /// The screenshot covers the width of the `scrollable` and the height of
+    /// its content. Its scroll offset is restored afterwards.
+    ///
+    /// The vertical scrollbar is cropped out, so it is not stitched once per
+    /// segment: the screenshot is narrowed to the content, if it leaves room
+    /// for the scrollbar, or by the width of the default scrollbar otherwise.
+    /// Content of height 0 cannot be captured.
+    pub fn screenshot_full_page(
+        &mut self,
+        id: impl Into<widget::Id>,
+    ) -> Result<window::Screenshot, Error> {
+        let id = id.into();
+
+        let mut find = query::FindScrollable {
+            target: id.clone(),
+            viewport: None,
+        };
+
+        self.ui.operate(&self.simulator.renderer, &mut find);
+
+        let viewport = find
+            .viewport
+            .ok_or(Error::NotFound(Selector::Id(id.clone())))?;
+
+        let max_offset = (viewport.content_bounds.height - viewport.bounds.height).max(0.0);
+        let total = (viewport.content_bounds.height * self.scale_factor).round() as u32;
+
+        // The vertical scrollbar is only drawn when the content overflows
+        let bounds = if max_offset > 0.0 {
+            Rectangle {
+                width: viewport
+                    .content_bounds
+                    .width
+                    .min(viewport.bounds.width - SCROLLBAR_WIDTH)
+                    .max(0.0),
+                ..viewport.bounds
+            }
+        } else {
+            viewport.bounds
+        };
+
+        if total == 0 || (bounds.width * self.scale_factor).round() < 1.0 {
+            return Err(Error::Layout {
+                size: Size::new(bounds.width, viewport.content_bounds.height),
+                scale_factor: self.scale_factor,
+            });
+        }
+
+        let mut rgba = Vec::new();
+        let mut width = 0;
+        let mut filled = 0;
+
+        while filled < total {
+            let offset = (filled as f32 / self.scale_factor).min(max_offset);
+
+            self.operate(Box::new(operation::scrollable::scroll_to(
+                id.clone(),
+                operation::scrollable::AbsoluteOffset {
+                    x: None,
+                    y: Some(offset),
+                },
+            )));
+
+            let segment = crop(&self.screenshot()?, bounds)?;
+
+            // The last segment may overlap the previous one
+            let skip = filled.saturating_sub((offset * self.scale_factor).round() as u32);
+            let rows = segment.size.height.saturating_sub(skip).min(total - filled);
+
+            if rows == 0 {
+                break;
+            }
+
+            let stride = segment.size.width as usize * 4;
+            let start = skip as usize * stride;
+
+            rgba.extend_from_slice(&segment.bytes[start..start + rows as usize * stride]);
+            width = segment.size.width;
+            filled += rows;
+        }
+
+        self.operate(Box::new(operation::scrollable::scroll_to(
+            id,
+            operation::scrollable::AbsoluteOffset {
+                x: Some(viewport.translation.x),
+                y: Some(viewport.translation.y),
+            },
+        )));
+
+        Ok(window::Screenshot::new(
+            rgba,
+            Size::new(width, filled),
+            f64::from(self.scale_factor),
+        ))
+    }
+
+    /// Takes a screenshot of the user interface in its current state
+    pub fn screenshot(&mut self) -> Result<window::Screenshot, Error> {
+        let physical_size = self.physical_size()?;
+        let simulator = &mut *self.simulator;
+        let base = simulator.theme.base();
+
+        self.ui.draw(
+            &mut simulator.renderer,
+            &simulator.theme,
             &renderer::Style {
                 text_color: base.text_color,
             },
-            self.cursor,
+            simulator.cursor,
         );
 
-        let physical_size = Size::new(
-            (size.width * scale_factor).round() as u32,
-            (size.height * scale_factor).round() as u32,
-        );
+        let background = simulator.background.color(&simulator.theme);
 
-        let rgba = self
+        let mut rgba = simulator
             .renderer
-            .screenshot(physical_size, scale_factor, base.background_color);
+            .screenshot(physical_size, self.scale_factor, background);
+
+        let expected = physical_size.width as usize * physical_size.height as usize * 4;
+
+        if rgba.len() != expected {
+            return Err(Error::Draw {
+                expected,
+                found: rgba.len(),
+            });
+        }
+
+        // Renderers blend with premultiplied alpha
+        if background.a < 1.0 {
+            unpremultiply(&mut rgba);
+        }
 
         Ok(window::Screenshot::new(
             rgba,
             physical_size,
-            f64::from(scale_factor),
+            f64::from(self.scale_factor),
         ))
     }
+
+    // Computes the size of the screenshot in physical pixels
+    fn physical_size(&self) -> Result<Size<u32>, Error> {
+        let width = (self.size.width * self.scale_factor).round();
+        let height = (self.size.height * self.scale_factor).round();
+
+        if !(width >= 1.0 && height >= 1.0 && width.is_finite() && height.is_finite()) {
+            return Err(Error::Layout {
+                size: self.size,
+                scale_factor: self.scale_factor,
+            });
+        }
+
+        Ok(Size::new(width as u32, height as u32))
+    }
+
+    /// Returns the logical size of the user interface
+    pub fn size(&self) -> Size {
+        self.size
+    }
+}
+
+fn press_key(key: keyboard::Key, text: Option<iced_runtime::core::SmolStr>) -> Event {
+    Event::Keyboard(keyboard::Event::KeyPressed {
+        modified_key: key.clone(),
+        physical_key: key::Physical::Unidentified(key::NativeCode::Unidentified),
+        key,
+        location: keyboard::Location::Standard,
+        modifiers: keyboard::Modifiers::default(),
+        text,
+        repeat: false,
+    })
+}
+
+fn release_key(key: keyboard::Key) -> Event {
+    Event::Keyboard(keyboard::Event::KeyReleased {
+        modified_key: key.clone(),
+        physical_key: key::Physical::Unidentified(key::NativeCode::Unidentified),
+        key,
+        location: keyboard::Location::Standard,
+        modifiers: keyboard::Modifiers::default(),
+    })
 }
 
-fn load_font(font: impl Into<Cow<'static, [u8]>>) -> Result<(), String> {
-    iced::advanced::graphics::text::font_system()
-        .write()
-        .expect("Write to font system")
-        .load_font(font.into());
+fn family_name(family: iced::font::Family) -> String {
+    match family {
+        iced::font::Family::Name(name) => name.to_owned(),
+        iced::font::Family::Serif => "serif".to_owned(),
+        iced::font::Family::SansSerif => "sans-serif".to_owned(),
+        iced::font::Family::Cursive => "cursive".to_owned(),
+        iced::font::Family::Fantasy => "fantasy".to_owned(),
+        iced::font::Family::Monospace => "monospace".to_owned(),
+    }
+}
+
+// Converts premultiplied RGBA pixels to straight alpha
+fn unpremultiply(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = u32::from(pixel[3]);
+
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+
+        for channel in &mut pixel[..3] {
+            *channel = ((u32::from(*channel) * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
+// Crops the screenshot to the given logical bounds, clamped to the screenshot
+fn crop(screenshot: &window::Screenshot, bounds: Rectangle) -> Result<window::Screenshot, Error> {
+    let scale_factor = screenshot.scale_factor as f32;
+    let size = screenshot.size;
+
+    let x = ((bounds.x * scale_factor).floor().max(0.0) as u32).min(size.width);
+    let y = ((bounds.y * scale_factor).floor().max(0.0) as u32).min(size.height);
+
+    let right = (((bounds.x + bounds.width) * scale_factor).ceil().max(0.0) as u32).min(size.width);
+    let bottom =
+        (((bounds.y + bounds.height) * scale_factor).ceil().max(0.0) as u32).min(size.height);
 
-    Ok(())
+    Ok(screenshot.crop(Rectangle {
+        x,
+        y,
+        width: right.saturating_sub(x),
+        height: bottom.saturating_sub(y),
+    })?)
 }
@@ -0,0 +1,40 @@
+//! Choose the renderer backend of a simulator
+use std::env;
+use std::fmt;
+
+/// The renderer backend used by a [`Simulator`](super::Simulator)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Let the renderer decide, honoring the `ICED_BACKEND` environment variable
+    #[default]
+    Default,
+    /// The `tiny-skia` software renderer
+    TinySkia,
+    /// The `wgpu` renderer
+    Wgpu,
+    /// A backend identified by the name its renderer expects
+    Named(String),
+}
+
+impl Backend {
+    /// Returns the name passed to the headless renderer, if any
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Backend::Default => env::var("ICED_BACKEND").ok(),
+            Backend::TinySkia => Some("tiny-skia".to_owned()),
+            Backend::Wgpu => Some("wgpu".to_owned()),
+            Backend::Named(name) => Some(name.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Default => write!(f, "default"),
+            Backend::TinySkia => write!(f, "tiny-skia"),
+            Backend::Wgpu => write!(f, "wgpu"),
+            Backend::Named(name) => write!(f, "{name}"),
+        }
+    }
+}
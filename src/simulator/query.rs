@@ -0,0 +1,285 @@
+//! Find widgets in a simulated user interface
+use iced::advanced::widget::operation::{Focusable, Scrollable, TextInput};
+use iced::advanced::widget::{self, Operation};
+use iced::{Rectangle, Vector};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// The kind of a widget, as reported by its widget operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Kind {
+    /// A widget with children, like a `container`, `row` or `button`
+    Container,
+    /// A `scrollable`
+    Scrollable,
+    /// A widget that can be focused
+    Focusable,
+    /// A `text_input`
+    TextInput,
+    /// A widget displaying text
+    Text,
+    /// A widget exposing custom state
+    Custom,
+    /// A widget that reports nothing to widget operations, like an `image`,
+    /// a `slider` or a `space`, of which only the layout is known
+    Unknown,
+}
+
+impl Kind {
+    // How much a kind tells about a widget, when it reports more than one
+    pub(crate) fn richness(self) -> u8 {
+        match self {
+            Kind::Unknown | Kind::Container => 0,
+            Kind::Focusable => 1,
+            Kind::Custom => 2,
+            Kind::Text => 3,
+            Kind::Scrollable => 4,
+            Kind::TextInput => 5,
+        }
+    }
+}
+
+/// A widget found in a user interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// The kind of the widget
+    pub kind: Kind,
+    /// The id of the widget, if any
+    pub id: Option<widget::Id>,
+    /// The layout bounds of the widget
+    pub bounds: Rectangle,
+    /// The text of the widget, if any
+    pub text: Option<String>,
+    /// The nesting depth of the widget in the widget tree
+    pub depth: usize,
+}
+
+/// A way of finding widgets in a user interface
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    /// Matches the widget with the given id
+    Id(widget::Id),
+    /// Matches the widgets displaying exactly the given text
+    Text(String),
+    /// Matches the widgets of the given kind
+    Kind(Kind),
+}
+
+impl Selector {
+    /// Returns whether the widget is matched by the [`Selector`]
+    pub fn matches(&self, candidate: &Match) -> bool {
+        match self {
+            Selector::Id(id) => candidate.id.as_ref() == Some(id),
+            Selector::Text(text) => candidate.text.as_deref() == Some(text),
+            Selector::Kind(kind) => candidate.kind == *kind,
+        }
+    }
+}
+
+impl From<widget::Id> for Selector {
+    fn from(id: widget::Id) -> Self {
+        Selector::Id(id)
+    }
+}
+
+impl From<&str> for Selector {
+    fn from(text: &str) -> Self {
+        Selector::Text(text.to_owned())
+    }
+}
+
+impl From<String> for Selector {
+    fn from(text: String) -> Self {
+        Selector::Text(text)
+    }
+}
+
+impl From<Kind> for Selector {
+    fn from(kind: Kind) -> Self {
+        Selector::Kind(kind)
+    }
+}
+
+// An operation that records every widget it visits, in traversal order
+#[derive(Default)]
+pub(super) struct Collect {
+    pub(super) matches: Vec<Match>,
+    depth: usize,
+}
+
+impl Collect {
+    fn record(
+        &mut self,
+        kind: Kind,
+        id: Option<&widget::Id>,
+        bounds: Rectangle,
+        text: Option<&str>,
+    ) {
+        // Some widgets report more than one operation, like a `text_input`
+        // that is also focusable; they are recorded once, with the richest kind
+        if let Some(last) = self.matches.last_mut().filter(|last| {
+            last.depth == self.depth
+                && last.bounds == bounds
+                && last.id.as_ref() == id
+                && last.kind != kind
+        }) {
+            if kind.richness() > last.kind.richness() {
+                last.kind = kind;
+            }
+
+            if last.text.is_none() {
+                last.text = text.map(str::to_owned);
+            }
+
+            return;
+        }
+
+        self.matches.push(Match {
+            kind,
+            id: id.cloned(),
+            bounds,
+            text: text.map(str::to_owned),
+            depth: self.depth,
+        });
+    }
+}
+
+impl Operation for Collect {
+    fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation)) {
+        self.depth += 1;
+        operate(self);
+        self.depth -= 1;
+    }
+
+    fn container(&mut self, id: Option<&widget::Id>, bounds: Rectangle) {
+        self.record(Kind::Container, id, bounds, None);
+    }
+
+    fn scrollable(
+        &mut self,
+        id: Option<&widget::Id>,
+        bounds: Rectangle,
+        _content_bounds: Rectangle,
+        _translation: Vector,
+        _state: &mut dyn Scrollable,
+    ) {
+        self.record(Kind::Scrollable, id, bounds, None);
+    }
+
+    fn focusable(
+        &mut self,
+        id: Option<&widget::Id>,
+        bounds: Rectangle,
+        _state: &mut dyn Focusable,
+    ) {
+        self.record(Kind::Focusable, id, bounds, None);
+    }
+
+    fn text_input(
+        &mut self,
+        id: Option<&widget::Id>,
+        bounds: Rectangle,
+        _state: &mut dyn TextInput,
+    ) {
+        self.record(Kind::TextInput, id, bounds, None);
+    }
+
+    fn text(&mut self, id: Option<&widget::Id>, bounds: Rectangle, text: &str) {
+        self.record(Kind::Text, id, bounds, Some(text));
+    }
+
+    fn custom(&mut self, id: Option<&widget::Id>, bounds: Rectangle, _state: &mut dyn Any) {
+        self.record(Kind::Custom, id, bounds, None);
+    }
+}
+
+/// The viewport of a `scrollable`, as reported by its widget operation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Viewport {
+    /// The bounds of the `scrollable`
+    pub bounds: Rectangle,
+    /// The bounds of its content
+    pub content_bounds: Rectangle,
+    /// The current scroll offset
+    pub translation: Vector,
+}
+
+/// An operation that finds the [`Viewport`] of the `scrollable` with the given id
+#[derive(Debug)]
+pub(crate) struct FindScrollable {
+    pub target: widget::Id,
+    pub viewport: Option<Viewport>,
+}
+
+impl Operation for FindScrollable {
+    fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation)) {
+        operate(self);
+    }
+
+    fn scrollable(
+        &mut self,
+        id: Option<&widget::Id>,
+        bounds: Rectangle,
+        content_bounds: Rectangle,
+        translation: Vector,
+        _state: &mut dyn Scrollable,
+    ) {
+        if self.viewport.is_none() && id == Some(&self.target) {
+            self.viewport = Some(Viewport {
+                bounds,
+                content_bounds,
+                translation,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::Dump;
+    use crate::lint::{self, Checks};
+    use iced::{Point, Size};
+
+    // Records a column with two text inputs, as reported by their operations
+    fn text_inputs() -> Collect {
+        let mut collect = Collect::default();
+
+        collect.container(None, Rectangle::new(Point::ORIGIN, Size::new(200.0, 100.0)));
+        collect.depth += 1;
+
+        for y in [0.0, 50.0] {
+            let bounds = Rectangle::new(Point::new(0.0, y), Size::new(200.0, 40.0));
+
+            collect.record(Kind::TextInput, None, bounds, None);
+            collect.record(Kind::Focusable, None, bounds, None);
+        }
+
+        collect
+    }
+
+    #[test]
+    fn text_input_is_a_single_widget() {
+        let kinds: Vec<_> = text_inputs().matches.iter().map(|m| m.kind).collect();
+
+        assert_eq!(kinds, [Kind::Container, Kind::TextInput, Kind::TextInput]);
+    }
+
+    #[test]
+    fn text_input_does_not_overlap_itself() {
+        let dump = Dump::from_matches(Size::new(200.0, 100.0), text_inputs().matches);
+
+        assert_eq!(lint::check(&dump, &Checks::default()), Vec::new());
+    }
+
+    #[test]
+    fn identical_siblings_of_the_same_kind_are_kept() {
+        let mut collect = Collect::default();
+        let bounds = Rectangle::new(Point::ORIGIN, Size::new(10.0, 10.0));
+
+        collect.record(Kind::Text, None, bounds, Some("a"));
+        collect.record(Kind::Text, None, bounds, Some("b"));
+
+        assert_eq!(collect.matches.len(), 2);
+    }
+}
@@ -0,0 +1,147 @@
+//! Keep the layout of a built user interface
+use iced::advanced::widget::{Operation, Tree, tree};
+use iced::advanced::{Clipboard, Layout, Shell, Widget, layout, overlay, renderer};
+use iced::{Element, Event, Length, Rectangle, Size, Theme, Vector, mouse};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The root of a user interface built by a [`Simulator`](super::Simulator)
+///
+/// It is transparent to the widget tree and only keeps a copy of the latest
+/// layout of its content, which [`UserInterface`](iced_runtime::UserInterface)
+/// does not expose.
+pub(crate) struct Root<'a, Message, R> {
+    content: Element<'a, Message, Theme, R>,
+    layout: Rc<RefCell<layout::Node>>,
+}
+
+impl<'a, Message, R> Root<'a, Message, R> {
+    /// Wraps the content, returning the shared copy of its layout
+    pub fn new(content: Element<'a, Message, Theme, R>) -> (Self, Rc<RefCell<layout::Node>>) {
+        let layout = Rc::new(RefCell::new(layout::Node::new(Size::ZERO)));
+
+        (
+            Self {
+                content,
+                layout: layout.clone(),
+            },
+            layout,
+        )
+    }
+}
+
+impl<Message, R> Widget<Message, Theme, R> for Root<'_, Message, R>
+where
+    R: renderer::Renderer,
+{
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn size_hint(&self) -> Size<Length> {
+        self.content.as_widget().size_hint()
+    }
+
+    fn layout(&mut self, tree: &mut Tree, renderer: &R, limits: &layout::Limits) -> layout::Node {
+        let node = self.content.as_widget_mut().layout(tree, renderer, limits);
+
+        *self.layout.borrow_mut() = node.clone();
+
+        node
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut R,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content
+            .as_widget()
+            .draw(tree, renderer, theme, style, layout, cursor, viewport);
+    }
+
+    fn tag(&self) -> tree::Tag {
+        self.content.as_widget().tag()
+    }
+
+    fn state(&self) -> tree::State {
+        self.content.as_widget().state()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.content.as_widget().children()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        self.content.as_widget().diff(tree);
+    }
+
+    fn operate(
+        &mut self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &R,
+        operation: &mut dyn Operation,
+    ) {
+        self.content
+            .as_widget_mut()
+            .operate(tree, layout, renderer, operation);
+    }
+
+    fn update(
+        &mut self,
+        tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &R,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget_mut().update(
+            tree, event, layout, cursor, renderer, clipboard, shell, viewport,
+        );
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &R,
+    ) -> mouse::Interaction {
+        self.content
+            .as_widget()
+            .mouse_interaction(tree, layout, cursor, viewport, renderer)
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'b>,
+        renderer: &R,
+        viewport: &Rectangle,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, R>> {
+        self.content
+            .as_widget_mut()
+            .overlay(tree, layout, renderer, viewport, translation)
+    }
+}
+
+impl<'a, Message, R> From<Root<'a, Message, R>> for Element<'a, Message, Theme, R>
+where
+    Message: 'a,
+    R: renderer::Renderer + 'a,
+{
+    fn from(root: Root<'a, Message, R>) -> Self {
+        Element::new(root)
+    }
+}
@@ -0,0 +1,273 @@
+//! Run `update`/`view` programs headlessly
+use iced::advanced::renderer;
+use iced::advanced::renderer::Headless;
+use iced::application::{BootFn, UpdateFn, ViewFn};
+use iced::futures::StreamExt;
+use iced::futures::stream::BoxStream;
+use iced::futures::task::{ArcWake, waker};
+use iced::time::Instant;
+use iced::{Event, Executor, Point, Task, Theme, Vector, window};
+use iced_runtime::{Action, task};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+use super::{Interface, Session, Simulator};
+use crate::Error;
+use crate::font;
+use crate::recording::Recording;
+
+/// A headless program driven by a [`Simulator`]
+///
+/// The [`Runner`] owns the state of the program, feeds the messages produced
+/// by injected events to its `update` logic and executes the resulting
+/// [`Task`]s.
+///
+/// Tasks are polled until none of them is ready, without waiting for them
+/// to finish; unfinished tasks stay pending. Use [`Runner::settle`] to wait
+/// for long-running tasks, like timers, with a timeout.
+pub struct Runner<'s, State, Message, R = iced::Renderer>
+where
+    R: renderer::Renderer + Headless + Send,
+{
+    session: Session<'s, R>,
+    state: State,
+    update: Box<dyn UpdateFn<State, Message> + 's>,
+    view: Box<dyn for<'a> ViewFn<'a, State, Message, Theme, R> + 's>,
+    executor: iced::executor::Default,
+    tasks: VecDeque<Task<Message>>,
+    pending: Vec<BoxStream<'static, Action<Message>>>,
+    exited: bool,
+}
+
+impl<R> Simulator<R>
+where
+    R: renderer::Renderer + Headless + Send + 'static,
+{
+    /// Boots the given program and runs its initial [`Task`]
+    pub fn run_program<'s, State, Message>(
+        &'s mut self,
+        boot: impl BootFn<State, Message>,
+        update: impl UpdateFn<State, Message> + 's,
+        view: impl for<'a> ViewFn<'a, State, Message, Theme, R> + 's,
+        size: impl Into<iced::Size>,
+        scale_factor: f32,
+    ) -> Result<Runner<'s, State, Message, R>, Error>
+    where
+        State: 'static,
+        Message: Send + 'static,
+    {
+        let (state, task) = boot.boot();
+
+        let executor = iced::executor::Default::new().map_err(Error::Executor)?;
+
+        let mut runner = Runner {
+            session: self.session(size, scale_factor),
+            state,
+            update: Box::new(update),
+            view: Box::new(view),
+            executor,
+            tasks: VecDeque::from([task]),
+            pending: Vec::new(),
+            exited: false,
+        };
+
+        runner.run_tasks();
+
+        Ok(runner)
+    }
+}
+
+impl<State, Message, R> Runner<'_, State, Message, R>
+where
+    State: 'static,
+    Message: Send + 'static,
+    R: renderer::Renderer + Headless + Send + 'static,
+{
+    /// Returns the current state of the program
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns whether the program has requested to exit
+    pub fn is_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Returns the amount of tasks that have not finished yet
+    pub fn pending_tasks(&self) -> usize {
+        self.pending.len() + self.tasks.len()
+    }
+
+    /// Polls the pending tasks once, performing the actions that are ready
+    /// until none of them is
+    pub fn step(&mut self) {
+        self.run_tasks();
+    }
+
+    /// Runs the pending tasks until all of them finish or the timeout
+    /// elapses, returning whether all of them finished
+    ///
+    /// The timeout is measured in real time, since tasks may wait on the
+    /// timers of the executor.
+    pub fn settle(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            self.run_tasks();
+
+            if self.pending_tasks() == 0 {
+                return true;
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                return false;
+            }
+
+            // Woken up as soon as any pending task is ready
+            thread::park_timeout(deadline - now);
+        }
+    }
+
+    /// Updates the program with the given message and runs the resulting tasks
+    pub fn update(&mut self, message: Message) {
+        let task = self.update.update(&mut self.state, message);
+
+        self.tasks.push_back(task);
+        self.run_tasks();
+    }
+
+    /// Feeds the given events to the program
+    pub fn simulate(&mut self, events: impl IntoIterator<Item = Event>) {
+        self.interact(|interface| interface.simulate(events));
+    }
+
+    /// Moves the cursor to the given position
+    pub fn move_cursor(&mut self, position: impl Into<Point>) {
+        self.interact(|interface| interface.move_cursor(position));
+    }
+
+    /// Moves the cursor to the given position and clicks the left mouse button
+    pub fn click(&mut self, position: impl Into<Point>) {
+        self.interact(|interface| interface.click(position));
+    }
+
+    /// Scrolls the mouse wheel by the given amount of pixels
+    pub fn scroll(&mut self, delta: impl Into<Vector>) {
+        self.interact(|interface| interface.scroll(delta));
+    }
+
+    /// Types the given text, one key press at a time
+    pub fn type_text(&mut self, text: &str) {
+        self.interact(|interface| interface.type_text(text));
+    }
+
+    /// Moves the clock of the [`Simulator`] forward and redraws the program
+    pub fn advance(&mut self, duration: Duration) {
+        self.interact(|interface| interface.advance(duration));
+    }
+
+    /// Takes a screenshot of the current view of the program
+    pub fn screenshot(&mut self) -> Result<window::Screenshot, Error> {
+        self.session.screenshot(self.view.view(&self.state))
+    }
+
+    /// Records the program over time, advancing the clock by the interval
+    /// between two frames
+    pub fn record(&mut self, frames: usize, interval: Duration) -> Result<Recording, Error> {
+        let mut recording = Recording {
+            frames: Vec::with_capacity(frames),
+            interval,
+        };
+
+        for frame in 0..frames {
+            if frame == 0 {
+                self.interact(|interface| interface.redraw());
+            } else {
+                self.advance(interval);
+            }
+
+            recording.frames.push(self.screenshot()?);
+        }
+
+        Ok(recording)
+    }
+
+    fn interact(&mut self, f: impl FnOnce(&mut Interface<'_, '_, Message, R>) -> Vec<Message>) {
+        let messages = self.session.interact(self.view.view(&self.state), f);
+
+        for message in messages {
+            self.update(message);
+        }
+    }
+
+    // Polls every pending task until none of them is ready
+    fn run_tasks(&mut self) {
+        loop {
+            self.pending
+                .extend(self.tasks.drain(..).filter_map(task::into_stream));
+
+            let waker = waker(Arc::new(Unpark(thread::current())));
+            let mut context = Context::from_waker(&waker);
+            let mut actions = Vec::new();
+            let pending = &mut self.pending;
+
+            Executor::enter(&self.executor, || {
+                pending.retain_mut(|stream| {
+                    loop {
+                        match stream.poll_next_unpin(&mut context) {
+                            Poll::Ready(Some(action)) => actions.push(action),
+                            Poll::Ready(None) => return false,
+                            Poll::Pending => return true,
+                        }
+                    }
+                });
+            });
+
+            if actions.is_empty() {
+                return;
+            }
+
+            for action in actions {
+                self.perform(action);
+            }
+        }
+    }
+
+    fn perform(&mut self, action: Action<Message>) {
+        match action {
+            Action::Output(message) => {
+                let task = self.update.update(&mut self.state, message);
+
+                self.tasks.push_back(task);
+            }
+            Action::LoadFont { bytes, channel } => {
+                // Font errors cannot be sent back, so failures drop the channel
+                let _ = font::load(bytes).map(|()| channel.send(Ok(())));
+            }
+            Action::Widget(operation) => {
+                self.session
+                    .interact(self.view.view(&self.state), |interface| {
+                        interface.operate(operation);
+                    });
+            }
+            Action::Exit => {
+                self.exited = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Wakes up the thread that runs the tasks
+struct Unpark(thread::Thread);
+
+impl ArcWake for Unpark {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.unpark();
+    }
+}
@@ -0,0 +1,375 @@
+//! Compare screenshots against golden PNG snapshots
+use iced::{Size, window};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+use crate::regression::{self, Case, Status};
+use crate::{diff, export, metrics};
+
+/// The environment variable that, when set to `1`, regenerates goldens
+pub const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// A golden PNG snapshot stored on disk
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    path: PathBuf,
+    tolerance: Tolerance,
+}
+
+/// How much a screenshot may differ from its golden and still match
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tolerance {
+    /// The maximum difference allowed in any RGBA channel of a pixel
+    pub channel: u8,
+    /// The amount of pixels allowed to exceed the channel tolerance
+    pub pixels: usize,
+    /// The structural similarity above which screenshots exceeding the pixel
+    /// tolerance are still accepted
+    ///
+    /// SSIM ignores alpha, so screenshots whose alpha exceeds the channel
+    /// tolerance are never accepted this way.
+    pub min_ssim: Option<f64>,
+}
+
+impl Tolerance {
+    /// Returns whether a screenshot with the given amount of pixels exceeding
+    /// the channel tolerance is accepted
+    pub fn accepts(
+        &self,
+        differing: usize,
+        golden: &window::Screenshot,
+        screenshot: &window::Screenshot,
+    ) -> Result<bool, Error> {
+        if differing <= self.pixels {
+            return Ok(true);
+        }
+
+        match self.min_ssim {
+            Some(min_ssim) => Ok(self.accepts_alpha(golden, screenshot)
+                && metrics::ssim(golden, screenshot)? >= min_ssim),
+            None => Ok(false),
+        }
+    }
+
+    fn accepts_alpha(&self, golden: &window::Screenshot, screenshot: &window::Screenshot) -> bool {
+        golden
+            .bytes
+            .chunks_exact(4)
+            .zip(screenshot.bytes.chunks_exact(4))
+            .all(|(g, s)| g[3].abs_diff(s[3]) <= self.channel)
+    }
+}
+
+/// The result of comparing a screenshot against a [`Snapshot`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The screenshot matched the golden within tolerance
+    Matched {
+        /// The amount of pixels that differed beyond the channel tolerance
+        differing: usize,
+    },
+    /// The golden was written from the screenshot
+    Updated,
+    /// The screenshot did not match the golden
+    Mismatched {
+        /// The amount of pixels that differed beyond the channel tolerance
+        differing: usize,
+        /// Where the actual screenshot was written
+        actual: PathBuf,
+        /// Where the diff image was written
+        diff: PathBuf,
+    },
+    /// There is no golden for this snapshot yet
+    Missing {
+        /// Where the actual screenshot was written
+        actual: PathBuf,
+    },
+    /// The screenshot and the golden have different dimensions
+    SizeMismatch {
+        /// The size of the golden
+        expected: Size<u32>,
+        /// The size of the screenshot
+        found: Size<u32>,
+        /// Where the actual screenshot was written
+        actual: PathBuf,
+    },
+}
+
+impl Outcome {
+    /// Returns whether the screenshot is considered a match
+    pub fn is_match(&self) -> bool {
+        matches!(self, Outcome::Matched { .. } | Outcome::Updated)
+    }
+}
+
+impl Snapshot {
+    /// Creates a [`Snapshot`] backed by the golden PNG at the given path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    /// Sets the maximum difference allowed in any RGBA channel of a pixel
+    pub fn channel_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance.channel = tolerance;
+        self
+    }
+
+    /// Sets the amount of pixels allowed to exceed the channel tolerance
+    pub fn pixel_tolerance(mut self, tolerance: usize) -> Self {
+        self.tolerance.pixels = tolerance;
+        self
+    }
+
+    /// Accepts screenshots exceeding the pixel tolerance as long as their
+    /// structural similarity to the golden is at least the given value and
+    /// their alpha is within the channel tolerance
+    ///
+    /// See [`metrics::ssim`].
+    pub fn min_ssim(mut self, ssim: f64) -> Self {
+        self.tolerance.min_ssim = Some(ssim);
+        self
+    }
+
+    /// Sets all the tolerances of the [`Snapshot`] at once
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns the path of the golden PNG
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compares the screenshot against the golden, writing the actual and
+    /// diff images next to it on failure.
+    ///
+    /// If [`UPDATE_ENV`] is set to `1`, the golden is overwritten instead.
+    pub fn compare(&self, screenshot: &window::Screenshot) -> Result<Outcome, Error> {
+        if should_update() {
+            export::save(screenshot, None, &self.path)?;
+            self.clean()?;
+
+            return Ok(Outcome::Updated);
+        }
+
+        let actual = self.sibling("actual");
+
+        if !self.path.exists() {
+            export::save(screenshot, None, &actual)?;
+
+            return Ok(Outcome::Missing { actual });
+        }
+
+        let (golden, _metadata) = export::load(&self.path)?;
+        let size = golden.size;
+
+        if size != screenshot.size {
+            export::save(screenshot, None, &actual)?;
+
+            return Ok(Outcome::SizeMismatch {
+                expected: size,
+                found: screenshot.size,
+                actual,
+            });
+        }
+
+        let differing = diff::diff(&golden, screenshot, self.tolerance.channel)?.over_threshold;
+
+        if self.tolerance.accepts(differing, &golden, screenshot)? {
+            self.clean()?;
+
+            return Ok(Outcome::Matched { differing });
+        }
+
+        let diff = self.sibling("diff");
+
+        export::save(screenshot, None, &actual)?;
+        export::save(
+            &window::Screenshot::new(
+                highlight(&golden, screenshot, self.tolerance.channel),
+                size,
+                screenshot.scale_factor,
+            ),
+            None,
+            &diff,
+        )?;
+
+        Ok(Outcome::Mismatched {
+            differing,
+            actual,
+            diff,
+        })
+    }
+
+    /// Compares the screenshot against the golden, panicking on mismatch
+    pub fn assert(&self, screenshot: &window::Screenshot) {
+        let outcome = self
+            .compare(screenshot)
+            .unwrap_or_else(|error| panic!("Snapshot {:?} failed: {error}", self.path));
+
+        match outcome {
+            Outcome::Matched { .. } | Outcome::Updated => {}
+            Outcome::Mismatched {
+                differing,
+                actual,
+                diff,
+            } => panic!(
+                "Snapshot {:?} mismatched: {differing} pixels differ (tolerance: {})\n\
+                - actual: {actual:?}\n\
+                - diff: {diff:?}\n\
+                Run with {UPDATE_ENV}=1 to accept the changes",
+                self.path, self.tolerance.pixels
+            ),
+            Outcome::Missing { actual } => panic!(
+                "Snapshot {:?} is missing, actual written to {actual:?}\n\
+                Run with {UPDATE_ENV}=1 to create it",
+                self.path
+            ),
+            Outcome::SizeMismatch {
+                expected,
+                found,
+                actual,
+            } => panic!(
+                "Snapshot {:?} expected size {expected:?}, found {found:?}\n\
+                - actual: {actual:?}",
+                self.path
+            ),
+        }
+    }
+
+    /// Turns the [`Outcome`] of a comparison against this [`Snapshot`] into a
+    /// [`Case`], for reports
+    pub fn case(&self, outcome: &Outcome) -> Result<Case, Error> {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut case = match outcome {
+            Outcome::Mismatched { actual, .. } => {
+                let (golden, _) = export::load(&self.path)?;
+                let (screenshot, _) = export::load(actual)?;
+
+                regression::compare(name, &golden, &screenshot, &self.tolerance)?
+            }
+            _ => Case {
+                name,
+                golden: None,
+                actual: None,
+                status: match outcome {
+                    Outcome::Missing { .. } => Status::New,
+                    Outcome::SizeMismatch {
+                        expected, found, ..
+                    } => Status::SizeMismatch {
+                        expected: *expected,
+                        found: *found,
+                    },
+                    _ => Status::Passed,
+                },
+                diff: None,
+                metrics: None,
+            },
+        };
+
+        case.golden = self.path.exists().then(|| self.path.clone());
+        case.actual = match outcome {
+            Outcome::Mismatched { actual, .. }
+            | Outcome::Missing { actual }
+            | Outcome::SizeMismatch { actual, .. } => Some(actual.clone()),
+            Outcome::Matched { .. } | Outcome::Updated => None,
+        };
+
+        Ok(case)
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        self.path.with_file_name(format!("{stem}.{suffix}.png"))
+    }
+
+    // Removes stale actual and diff images from a previous failure
+    fn clean(&self) -> Result<(), Error> {
+        for path in [self.sibling("actual"), self.sibling("diff")] {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|error| Error::Io { path, error })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether goldens should be regenerated
+pub fn should_update() -> bool {
+    env::var(UPDATE_ENV).is_ok_and(|value| value == "1")
+}
+
+// Builds a diff image highlighting the pixels counted by `diff::diff` in red
+// over a faded copy of the expected pixels
+fn highlight(expected: &window::Screenshot, actual: &window::Screenshot, threshold: u8) -> Vec<u8> {
+    let mut image = Vec::with_capacity(expected.bytes.len());
+
+    for (error, e) in diff::errors(expected, actual).zip(expected.bytes.chunks_exact(4)) {
+        if error > threshold {
+            image.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma =
+                (0.299 * f32::from(e[0]) + 0.587 * f32::from(e[1]) + 0.114 * f32::from(e[2])) as u8;
+            let faded = 191 + luma / 4;
+
+            image.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_ssim_does_not_accept_alpha_changes() {
+        let golden = window::Screenshot::new([10, 20, 30, 255].repeat(4), Size::new(2, 2), 1.0);
+        let transparent = window::Screenshot::new([10, 20, 30, 0].repeat(4), Size::new(2, 2), 1.0);
+
+        let tolerance = Tolerance {
+            min_ssim: Some(0.9),
+            ..Tolerance::default()
+        };
+
+        assert!(tolerance.accepts(4, &golden, &golden).unwrap());
+        assert!(!tolerance.accepts(4, &golden, &transparent).unwrap());
+    }
+
+    #[test]
+    fn highlights_the_pixels_counted_by_the_diff() {
+        let golden = window::Screenshot::new([0, 0, 0, 255].repeat(3), Size::new(3, 1), 1.0);
+        let actual = window::Screenshot::new(
+            [[0, 0, 0, 255], [0, 0, 2, 255], [0, 9, 0, 255]].concat(),
+            Size::new(3, 1),
+            1.0,
+        );
+
+        let image = highlight(&golden, &actual, 2);
+        let red: Vec<_> = image
+            .chunks_exact(4)
+            .map(|pixel| pixel == [255, 0, 0, 255])
+            .collect();
+
+        assert_eq!(diff::diff(&golden, &actual, 2).unwrap().over_threshold, 1);
+        assert_eq!(red, [false, false, true]);
+    }
+}